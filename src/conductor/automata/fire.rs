@@ -0,0 +1,43 @@
+use crate::automata::{AutomatonRule, Intent, Neighbourhood};
+use crate::common::Position;
+use fastrand::Rng;
+
+// Chance out of 100 to give off smoke each tick.
+const SMOKE_CHANCE: u8 = 20;
+// Chance out of 100 to burn out each tick once there is nothing left to burn.
+const STARVE_CHANCE: u8 = 10;
+
+pub struct Fire;
+
+impl AutomatonRule for Fire {
+    // Spreads to flammable neighbours, consuming them, and smokes into the cell above.
+    fn update(&self, view: &Neighbourhood, rng: &Rng, intents: &mut Vec<Intent>) {
+        let mut starving = true;
+        for offset in Neighbourhood::moore() {
+            if let Some(fuel) = view.cell(offset).and_then(|cell| cell.material) {
+                let flammability = view
+                    .material(offset)
+                    .map_or(0, |material| material.flammability);
+                starving &= flammability == 0;
+                if rng.u8(0..100) < flammability {
+                    intents.push(Intent::Transform {
+                        offset,
+                        from: fuel,
+                        into: view.automata,
+                    });
+                }
+            }
+        }
+        if let Some(smoke) = view.this().emits {
+            if rng.u8(0..100) < SMOKE_CHANCE {
+                intents.push(Intent::Spawn {
+                    offset: Position::new(rng.i64(-1..=1), -1),
+                    material: smoke,
+                });
+            }
+        }
+        if starving && rng.u8(0..100) < STARVE_CHANCE {
+            intents.push(Intent::Die);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use crate::automata::{AutomatonRule, Intent, Neighbourhood};
+use crate::common::Position;
+use fastrand::Rng;
+
+pub struct Gas;
+
+impl AutomatonRule for Gas {
+    // Rises with a bit of random drift, wandering sideways when something is above.
+    fn update(&self, view: &Neighbourhood, rng: &Rng, intents: &mut Vec<Intent>) {
+        let up = Position::new(rng.i64(-1..=1), -1);
+        if view.passable(up) {
+            intents.push(Intent::Swap(up));
+            return;
+        }
+        let side = Position::new(rng.i64(-1..=1), 0);
+        if view.free(side) {
+            intents.push(Intent::Move(side));
+        }
+    }
+}
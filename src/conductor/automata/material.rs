@@ -0,0 +1,320 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::automata::fire::Fire;
+use crate::automata::gas::Gas;
+use crate::automata::random_walker::RandomWalker;
+use crate::automata::sand::Sand;
+use crate::automata::solid::Static;
+use crate::automata::water::Water;
+use crate::automata::{Automata, AutomatonRule};
+use crate::grid::AMBIENT;
+use pixelbuffer::Pixel;
+
+// Shipped with the binary, used when there is no definition file next to it.
+const DEFAULT_DEFINITIONS: &str = include_str!("../../../materials.txt");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Solid,
+    Powder,
+    Liquid,
+    Gas,
+}
+
+/// Built-in update rule a material is simulated with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    RandomWalker,
+    Sand,
+    Water,
+    Gas,
+    Fire,
+    // Never moves or updates, the cell is all there is to it.
+    Static,
+}
+
+impl Rule {
+    pub fn automaton(self) -> &'static dyn AutomatonRule {
+        match self {
+            Self::RandomWalker => &RandomWalker,
+            Self::Sand => &Sand,
+            Self::Water => &Water,
+            Self::Gas => &Gas,
+            Self::Fire => &Fire,
+            Self::Static => &Static,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub name: String,
+    pub palette: Vec<[u8; 3]>,
+    pub variance: [u8; 3],
+    pub density: u16,
+    // How many cells a liquid may flow sideways in one tick.
+    pub dispersion: i64,
+    pub phase: Phase,
+    pub rule: Rule,
+    // Ticks before the automaton dissipates, 0 means it lives forever.
+    pub lifetime: u32,
+    // Percent chance per tick to catch fire next to a burning cell.
+    pub flammability: u8,
+    // Material given off while burning, like smoke.
+    pub emits: Option<Automata>,
+    // Share of a temperature difference passed on to a neighbour per tick, from 0 to 1.
+    pub conductivity: f32,
+    // Temperature a freshly placed automaton starts at.
+    pub temperature: f32,
+    // Phase changes, into the material once hotter or colder than the threshold.
+    pub above: Option<(f32, Automata)>,
+    pub below: Option<(f32, Automata)>,
+}
+
+impl Material {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            palette: Vec::new(),
+            variance: [0; 3],
+            density: 0,
+            dispersion: 1,
+            phase: Phase::Powder,
+            rule: Rule::Sand,
+            lifetime: 0,
+            flammability: 0,
+            emits: None,
+            conductivity: 0.5,
+            temperature: AMBIENT,
+            above: None,
+            below: None,
+        }
+    }
+
+    // Sets a property from the definition file, returning links to other materials for later.
+    fn set(&mut self, key: &str, value: &str) -> Result<Option<(Link, String)>, String> {
+        match key {
+            "color" => self.palette.push(parse_rgb(value)?),
+            "variance" => self.variance = parse_rgb(value)?,
+            "density" => self.density = parse_number(key, value)?,
+            "dispersion" => self.dispersion = parse_number(key, value)?,
+            "lifetime" => self.lifetime = parse_number(key, value)?,
+            "flammability" => {
+                self.flammability = parse_number(key, value)
+                    .ok()
+                    .filter(|chance| *chance <= 100)
+                    .ok_or_else(|| format!("invalid flammability {value}"))?;
+            }
+            "emits" => return Ok(Some((Link::Emits, value.to_owned()))),
+            "conductivity" => {
+                self.conductivity = parse_number(key, value)
+                    .ok()
+                    .filter(|conductivity| (0.0..=1.0).contains(conductivity))
+                    .ok_or_else(|| format!("invalid conductivity {value}"))?;
+            }
+            "temperature" => self.temperature = parse_number(key, value)?,
+            "above" | "below" => {
+                let (threshold, name) = value
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| format!("expected `temperature material`, got {value}"))?;
+                let threshold = parse_number(key, threshold)?;
+                let link = if key == "above" {
+                    Link::Above(threshold)
+                } else {
+                    Link::Below(threshold)
+                };
+                return Ok(Some((link, name.trim().to_owned())));
+            }
+            "phase" => {
+                self.phase = match value {
+                    "solid" => Phase::Solid,
+                    "powder" => Phase::Powder,
+                    "liquid" => Phase::Liquid,
+                    "gas" => Phase::Gas,
+                    _ => return Err(format!("unknown phase {value}")),
+                };
+            }
+            "rule" => {
+                self.rule = match value {
+                    "walker" => Rule::RandomWalker,
+                    "sand" => Rule::Sand,
+                    "water" => Rule::Water,
+                    "gas" => Rule::Gas,
+                    "fire" => Rule::Fire,
+                    "static" => Rule::Static,
+                    _ => return Err(format!("unknown rule {value}")),
+                };
+            }
+            _ => return Err(format!("unknown property {key}")),
+        }
+        Ok(None)
+    }
+
+    // The material this one turns into at the given temperature, if any.
+    pub fn phase_change(&self, temperature: f32) -> Option<Automata> {
+        match (self.above, self.below) {
+            (Some((threshold, into)), _) if temperature > threshold => Some(into),
+            (_, Some((threshold, into))) if temperature < threshold => Some(into),
+            _ => None,
+        }
+    }
+
+    // Denser materials sink through lighter ones, as long as the lighter one can flow.
+    pub fn displaces(&self, other: &Self) -> bool {
+        self.phase != Phase::Solid && other.phase != Phase::Solid && self.density > other.density
+    }
+
+    // Picks the color for a cell from the shade rolled when it was filled.
+    pub fn color(&self, shade: u8) -> Pixel {
+        let [r, g, b] = self
+            .palette
+            .get(usize::from(shade) % self.palette.len().max(1))
+            .copied()
+            .unwrap_or([255, 255, 255]);
+        let [vr, vg, vb] = self.variance;
+        let offset = |variance: u8| shade % variance.saturating_add(1);
+        Pixel::new(
+            r.saturating_add(offset(vr)),
+            g.saturating_add(offset(vg)),
+            b.saturating_add(offset(vb)),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum MaterialError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Io(ref err) => write!(f, "couldn't read material definitions: {err}"),
+            Self::Parse { line, ref message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for MaterialError {}
+
+impl From<std::io::Error> for MaterialError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+// Properties naming another material, resolved once every material is known.
+enum Link {
+    Emits,
+    Above(f32),
+    Below(f32),
+}
+
+/// All materials known to the simulation, indexed by `Automata`.
+pub struct MaterialRegistry {
+    materials: Vec<Material>,
+}
+
+impl MaterialRegistry {
+    /// Reads definitions from `path`, falling back to the built-in ones if it doesn't exist.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MaterialError> {
+        match std::fs::read_to_string(path) {
+            Ok(definitions) => Self::parse(&definitions),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Self::parse(DEFAULT_DEFINITIONS)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn parse(definitions: &str) -> Result<Self, MaterialError> {
+        let mut materials: Vec<Material> = Vec::new();
+        // Materials can refer to ones defined later, so names are resolved at the end.
+        let mut references: Vec<(usize, usize, Link, String)> = Vec::new();
+        for (index, line) in definitions.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: String| MaterialError::Parse {
+                line: line_number,
+                message,
+            };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let name = name.trim();
+                if materials.iter().any(|m| m.name == name) {
+                    return Err(error(format!("material {name} is defined twice")));
+                }
+                materials.push(Material::new(name));
+                continue;
+            }
+            let current = materials.len().saturating_sub(1);
+            let material = materials
+                .last_mut()
+                .ok_or_else(|| error("property outside of a [material] block".to_owned()))?;
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected `key = value`, got {line}")))?;
+            let (key, value) = (key.trim(), value.trim());
+            if let Some((link, name)) = material.set(key, value).map_err(error)? {
+                references.push((current, line_number, link, name));
+            }
+        }
+        if materials.is_empty() {
+            return Err(MaterialError::Parse {
+                line: 0,
+                message: "no materials defined".to_owned(),
+            });
+        }
+        let mut registry = Self { materials };
+        for (index, line, link, name) in references {
+            let other = registry.find(&name).ok_or_else(|| MaterialError::Parse {
+                line,
+                message: format!("unknown material {name}"),
+            })?;
+            if let Some(material) = registry.materials.get_mut(index) {
+                match link {
+                    Link::Emits => material.emits = Some(other),
+                    Link::Above(threshold) => material.above = Some((threshold, other)),
+                    Link::Below(threshold) => material.below = Some((threshold, other)),
+                }
+            }
+        }
+        Ok(registry)
+    }
+
+    #[allow(clippy::indexing_slicing)]
+    pub fn get(&self, id: Automata) -> &Material {
+        &self.materials[id.index()]
+    }
+
+    pub fn find(&self, name: &str) -> Option<Automata> {
+        self.materials
+            .iter()
+            .position(|m| m.name == name)
+            .map(Automata::from_index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+}
+
+fn parse_number<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid {key} {value}"))
+}
+
+pub fn parse_rgb(value: &str) -> Result<[u8; 3], String> {
+    let channels: Vec<u8> = value
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("invalid color {value}"))?;
+    channels
+        .try_into()
+        .map_err(|_| format!("expected three channels, got {value}"))
+}
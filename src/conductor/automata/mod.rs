@@ -1,68 +1,146 @@
-use std::convert::From;
-use std::ops::{Add, AddAssign};
-
+use crate::automata::material::{Material, MaterialRegistry};
 use crate::common::Position;
+use crate::grid::{Cell, Grid};
+use fastrand::Rng;
+use hecs::World as Ecs;
 
+pub mod fire;
+pub mod gas;
+pub mod material;
 pub mod random_walker;
 pub mod sand;
+pub mod solid;
 pub mod water;
 
-#[derive(Clone, Copy, Debug)]
-pub enum Automata {
-    RandomWalker,
-    Water,
-    Sand,
-}
-
-#[derive(Copy, Clone, Debug)]
-pub struct Destination {
-    pub x: i64,
-    pub y: i64,
-}
+/// Id of a material in the `MaterialRegistry`, stored on every automaton and grid cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Automata(u16);
 
-impl Destination {
-    pub const fn new(x: i64, y: i64) -> Self {
-        Self { x, y }
+impl Automata {
+    pub fn from_index(index: usize) -> Self {
+        Self(u16::try_from(index).expect("too many materials"))
     }
-    pub const fn distance(&self, rhs: &Self) -> i64 {
-        (self.x - rhs.x).abs() + (self.y - rhs.y).abs()
+
+    pub fn index(self) -> usize {
+        usize::from(self.0)
     }
 }
 
-impl From<Position> for Destination {
-    fn from(pos: Position) -> Self {
-        Self { x: pos.x, y: pos.y }
-    }
+/// Read-only view of the cells around an automaton, addressed by offsets from its position.
+pub struct Neighbourhood<'a> {
+    pub pos: Position,
+    pub automata: Automata,
+    grid: &'a Grid,
+    materials: &'a MaterialRegistry,
+    ecs: &'a Ecs,
 }
 
-impl From<Destination> for Position {
-    fn from(dest: Destination) -> Self {
+impl<'a> Neighbourhood<'a> {
+    pub const fn new(
+        pos: Position,
+        automata: Automata,
+        grid: &'a Grid,
+        materials: &'a MaterialRegistry,
+        ecs: &'a Ecs,
+    ) -> Self {
         Self {
-            x: dest.x,
-            y: dest.y,
+            pos,
+            automata,
+            grid,
+            materials,
+            ecs,
         }
     }
-}
 
-impl From<&Destination> for (i64, i64) {
-    fn from(from: &Destination) -> (i64, i64) {
-        (from.x, from.y)
+    pub fn this(&self) -> &'a Material {
+        self.materials.get(self.automata)
     }
-}
 
-impl Add for Destination {
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
+    // Material, temperature and the rest of the cell at `offset`.
+    pub fn cell(&self, offset: Position) -> Option<&'a Cell> {
+        self.grid.get(self.pos + offset)
+    }
+
+    // The material at `offset`, `None` for empty cells and outside the grid.
+    pub fn material(&self, offset: Position) -> Option<&'a Material> {
+        self.cell(offset)
+            .and_then(|cell| cell.material)
+            .map(|material| self.materials.get(material))
+    }
+
+    // Velocity of the automaton at `offset`, `None` if it has none.
+    pub fn velocity(&self, offset: Position) -> Option<Velocity> {
+        let entity = self.cell(offset)?.entity?;
+        self.ecs
+            .get::<Velocity>(entity)
+            .ok()
+            .map(|velocity| *velocity)
     }
+
+    pub fn free(&self, offset: Position) -> bool {
+        self.grid.free(self.pos + offset)
+    }
+
+    // True if the automaton could move or swap into `offset`.
+    pub fn passable(&self, offset: Position) -> bool {
+        self.grid
+            .can_enter(self.pos + offset, self.automata, self.materials)
+    }
+
+    // Offsets of the eight surrounding cells.
+    pub fn moore() -> impl Iterator<Item = Position> {
+        (-1..=1)
+            .flat_map(|y| (-1..=1).map(move |x| Position::new(x, y)))
+            .filter(|offset| offset.x != 0 || offset.y != 0)
+    }
+}
+
+/// Change an automaton wants to make to the world, offsets are relative to its position.
+#[derive(Clone, Copy, Debug)]
+pub enum Intent {
+    // Step into an empty cell.
+    Move(Position),
+    // Trade places with a lighter occupant, or step in if the cell is empty.
+    Swap(Position),
+    // Replace the automaton at the offset with a new one, if it is still made of `from`.
+    Transform {
+        offset: Position,
+        from: Automata,
+        into: Automata,
+    },
+    // Create an automaton at the offset if the cell is free.
+    Spawn {
+        offset: Position,
+        material: Automata,
+    },
+    // Remove the automaton from the world.
+    Die,
+}
+
+/// Behaviour of a material, asked every tick what its automata want to do.
+/// Any randomness has to come from `rng` so a seeded world replays the same way.
+pub trait AutomatonRule {
+    fn update(&self, view: &Neighbourhood, rng: &Rng, intents: &mut Vec<Intent>);
+}
+
+// Acceleration in cells per tick, per tick.
+pub const GRAVITY: f32 = 0.2;
+// Fastest an automaton can fall, in cells per tick.
+pub const TERMINAL_VELOCITY: f32 = 6.0;
+
+/// Speed of a falling automaton in cells per tick.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Velocity {
+    pub y: f32,
 }
 
-impl AddAssign for Destination {
-    fn add_assign(&mut self, rhs: Self) {
-        self.x += rhs.x;
-        self.y += rhs.y;
+impl Velocity {
+    // Cells to cover this tick when falling, at least one.
+    pub fn fall_distance(self) -> i64 {
+        (self.y.ceil() as i64).max(1)
     }
 }
+
+/// Ticks left until the automaton dissipates.
+#[derive(Copy, Clone, Debug)]
+pub struct Lifetime(pub u32);
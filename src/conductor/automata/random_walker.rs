@@ -1,10 +1,16 @@
-use crate::automata::Destination;
+use crate::automata::{AutomatonRule, Intent, Neighbourhood};
 use crate::common::Position;
-use fastrand;
+use fastrand::Rng;
 
 const SPEED: i64 = 1;
-pub fn update(pos: &Position) -> Destination {
-    Destination::from(
-        *pos + Position::new(fastrand::i64(-SPEED..=SPEED), fastrand::i64(-SPEED..=SPEED)),
-    )
+
+pub struct RandomWalker;
+
+impl AutomatonRule for RandomWalker {
+    fn update(&self, _view: &Neighbourhood, rng: &Rng, intents: &mut Vec<Intent>) {
+        intents.push(Intent::Move(Position::new(
+            rng.i64(-SPEED..=SPEED),
+            rng.i64(-SPEED..=SPEED),
+        )));
+    }
 }
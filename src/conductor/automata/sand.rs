@@ -1,7 +1,25 @@
-use crate::automata::Destination;
+use crate::automata::{AutomatonRule, Intent, Neighbourhood};
 use crate::common::Position;
-use fastrand;
+use fastrand::Rng;
 
-pub fn update(pos: &Position) -> Destination {
-    Destination::from(*pos + Position::new(fastrand::i64(-1..2), 1))
+pub struct Sand;
+
+impl AutomatonRule for Sand {
+    // Falls straight down if it can, otherwise slides off to a random side.
+    fn update(&self, view: &Neighbourhood, rng: &Rng, intents: &mut Vec<Intent>) {
+        let below = Position::new(0, 1);
+        if view.passable(below) {
+            let velocity = view.velocity(Position::new(0, 0)).unwrap_or_default();
+            intents.push(Intent::Swap(Position::new(0, velocity.fall_distance())));
+            return;
+        }
+        let side = if rng.bool() { 1 } else { -1 };
+        let below = [side, -side]
+            .into_iter()
+            .map(|x| Position::new(x, 1))
+            .find(|offset| view.passable(*offset));
+        if let Some(below) = below {
+            intents.push(Intent::Swap(below));
+        }
+    }
 }
@@ -0,0 +1,9 @@
+use crate::automata::{AutomatonRule, Intent, Neighbourhood};
+use fastrand::Rng;
+
+pub struct Static;
+
+impl AutomatonRule for Static {
+    // Static cells get no entity, so this is never asked, there would be nothing to do anyway.
+    fn update(&self, _view: &Neighbourhood, _rng: &Rng, _intents: &mut Vec<Intent>) {}
+}
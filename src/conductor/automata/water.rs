@@ -1,7 +1,42 @@
-use crate::automata::Destination;
+use crate::automata::{AutomatonRule, Intent, Neighbourhood};
 use crate::common::Position;
-use fastrand;
+use fastrand::Rng;
 
-pub fn update(pos: &Position) -> Destination {
-    Destination::from(*pos + Position::new(fastrand::i64(-1..2), fastrand::i64(0..2)))
+pub struct Water;
+
+impl AutomatonRule for Water {
+    // Falls if it can, otherwise flows up to `dispersion` cells sideways so pools level out.
+    fn update(&self, view: &Neighbourhood, rng: &Rng, intents: &mut Vec<Intent>) {
+        let below = Position::new(0, 1);
+        if view.passable(below) {
+            let velocity = view.velocity(Position::new(0, 0)).unwrap_or_default();
+            intents.push(Intent::Swap(Position::new(0, velocity.fall_distance())));
+            return;
+        }
+        let side = if rng.bool() { 1 } else { -1 };
+        for dir in [side, -side] {
+            let diagonal = Position::new(dir, 1);
+            if view.passable(diagonal) {
+                intents.push(Intent::Swap(diagonal));
+                return;
+            }
+        }
+        for dir in [side, -side] {
+            let mut dest = Position::new(0, 0);
+            for step in 1..=view.this().dispersion {
+                let next = Position::new(dir * step, 0);
+                if !view.free(next) {
+                    break;
+                }
+                dest = next;
+                if view.passable(next + below) {
+                    break;
+                }
+            }
+            if dest.x != 0 {
+                intents.push(Intent::Move(dest));
+                return;
+            }
+        }
+    }
 }
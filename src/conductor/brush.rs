@@ -0,0 +1,178 @@
+use crate::common::Position;
+
+// Largest radius the brush can be grown to, in cells.
+const MAX_RADIUS: i64 = 32;
+// Spray density, in percent, a new brush starts with.
+const DEFAULT_DENSITY: u8 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    Circle,
+    Square,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    // Paints every cell under the brush.
+    Fill,
+    // Paints each cell under the brush with the brush's density as percent chance per tick.
+    Spray,
+}
+
+/// What a stroke of the brush does to the cells it covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    // Fills empty cells with the selected material.
+    Paint,
+    // Removes whatever is in the cells.
+    Erase,
+}
+
+/// Tool the mouse paints materials into the world with.
+#[derive(Clone, Copy, Debug)]
+pub struct Brush {
+    pub radius: i64,
+    pub shape: Shape,
+    pub mode: Mode,
+    // Percent chance spraying paints a cell.
+    pub density: u8,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            radius: 3,
+            shape: Shape::Circle,
+            mode: Mode::Fill,
+            density: DEFAULT_DENSITY,
+        }
+    }
+}
+
+impl Brush {
+    pub fn resize(&mut self, step: i64) {
+        self.radius = (self.radius + step).clamp(0, MAX_RADIUS);
+    }
+
+    pub const fn toggle_shape(&mut self) {
+        self.shape = match self.shape {
+            Shape::Circle => Shape::Square,
+            Shape::Square => Shape::Circle,
+        };
+    }
+
+    pub const fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            Mode::Fill => Mode::Spray,
+            Mode::Spray => Mode::Fill,
+        };
+    }
+
+    pub fn change_density(&mut self, step: i16) {
+        let density = (i16::from(self.density) + step).clamp(1, 100);
+        self.density = u8::try_from(density).unwrap_or(DEFAULT_DENSITY);
+    }
+
+    // Offsets from the center covered by the brush.
+    fn footprint(self) -> impl Iterator<Item = Position> {
+        let Self { radius, shape, .. } = self;
+        (-radius..=radius)
+            .flat_map(move |y| (-radius..=radius).map(move |x| Position::new(x, y)))
+            .filter(move |offset| match shape {
+                Shape::Square => true,
+                // The extra `radius` rounds off the tips a one-cell-wide circle would have.
+                Shape::Circle => offset.x * offset.x + offset.y * offset.y <= radius * (radius + 1),
+            })
+    }
+
+    // Every cell the brush covers while dragged from `from` to `to`, each listed once in row order.
+    // The path in between is traced so fast strokes leave no gaps.
+    pub fn stroke(self, from: Position, to: Position) -> Vec<Position> {
+        let mut cells: Vec<Position> = from
+            .straight_line(to)
+            .into_iter()
+            .flat_map(|center| self.footprint().map(move |offset| center + offset))
+            .collect();
+        cells.sort_unstable_by_key(|pos| (pos.y, pos.x));
+        cells.dedup();
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn density_is_clamped() {
+        let mut brush = Brush::default();
+        brush.change_density(-50);
+        assert_eq!(brush.density, 1);
+        brush.change_density(500);
+        assert_eq!(brush.density, 100);
+        brush.change_density(-5);
+        assert_eq!(brush.density, 95);
+    }
+
+    #[test]
+    fn circle_is_inside_square() {
+        let circle = Brush::default();
+        let square = Brush {
+            shape: Shape::Square,
+            ..circle
+        };
+        let circle: Vec<Position> = circle.footprint().collect();
+        let square: Vec<Position> = square.footprint().collect();
+        assert_eq!(square.len(), 7 * 7);
+        assert!(circle.iter().all(|offset| square.contains(offset)));
+        assert!(circle.contains(&Position::new(3, 0)));
+        assert!(circle.contains(&Position::new(0, -3)));
+        assert!(!circle.contains(&Position::new(3, 3)));
+    }
+
+    #[test]
+    fn single_cell_brush() {
+        let brush = Brush {
+            radius: 0,
+            ..Brush::default()
+        };
+        let cell = Position::new(4, 5);
+        assert_eq!(brush.stroke(cell, cell), vec![cell]);
+    }
+
+    #[test]
+    fn long_stroke_leaves_no_gaps() {
+        let brush = Brush {
+            radius: 0,
+            ..Brush::default()
+        };
+        let from = Position::new(0, 0);
+        let to = Position::new(40, 13);
+        let mut by_column = brush.stroke(from, to);
+        assert!(by_column.contains(&from) && by_column.contains(&to));
+        // One cell per column, each next to the one before.
+        assert_eq!(by_column.len(), 41);
+        by_column.sort_unstable_by_key(|pos| pos.x);
+        for pair in by_column.windows(2) {
+            if let [a, b] = *pair {
+                assert_eq!(b.x - a.x, 1);
+                assert!((b.y - a.y).abs() <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn overlapping_stamps_are_listed_once() {
+        let brush = Brush::default();
+        let cells = brush.stroke(Position::new(10, 10), Position::new(20, 10));
+        // Sorted in row order without repeats.
+        for pair in cells.windows(2) {
+            if let [a, b] = *pair {
+                assert!((a.y, a.x) < (b.y, b.x));
+            }
+        }
+        // Every cell between the two ends is covered.
+        assert!((7..=23).all(|x| cells.contains(&Position::new(x, 10))));
+        assert!((10..=20).all(|x| cells.contains(&Position::new(x, 13))));
+    }
+}
@@ -1,7 +1,7 @@
 use std::convert::From;
 use std::ops::{Add, AddAssign};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Position {
     pub x: i64,
     pub y: i64,
@@ -16,58 +16,20 @@ impl Position {
         (self.x - rhs.x).abs() + (self.y - rhs.y).abs()
     }
 
-    // Returns a vector of positions starting from the end and going back to the start in a straight line.
+    // Returns the positions on a straight line from the start to the end point, both included,
+    // so a line to the start itself is just that one position.
     pub fn straight_line(self, end_point: Self) -> Vec<Self> {
-        let (dest_x, dest_y) = end_point.into();
-        let (start_x, start_y) = self.into();
-        let (delta_x, delta_y) = ((dest_x - start_x), (dest_y - start_y));
-        if delta_x == 0 && delta_y == 0 {
-            return Vec::new();
-        }
-
-        let spacing = if delta_x == 0 || delta_y == 0 {
-            1.0
-        } else {
-            let dx = delta_x.abs() as f64;
-            let dy = delta_y.abs() as f64;
-            (dx / dy).max(dy / dx)
-        };
-
+        let (delta_x, delta_y) = (end_point.x - self.x, end_point.y - self.y);
         let len = delta_x.abs().max(delta_y.abs());
-        let mut result: Vec<Self> = Vec::with_capacity(len as usize);
-
-        let x_step = delta_x.signum();
-        let x_iter = num_iter::range_step_inclusive(start_x, dest_x, x_step);
-        let y_step = delta_y.signum();
-        let y_iter = num_iter::range_step_inclusive(start_y, dest_y, y_step);
-
-        let mut counter = 0.0;
-        if delta_x.abs() > delta_y.abs() {
-            let mut y = start_y;
-            let last = vec![dest_y];
-            let mut short_range = y_iter.chain(last.iter().cycle().copied());
-            result.extend(x_iter.map(|x| {
-                counter += 1.0;
-                while counter > spacing {
-                    y = short_range.next().expect("iterator should be endless");
-                    counter -= spacing;
-                }
-                Self::new(x, y)
-            }));
-        } else {
-            let mut x = start_x;
-            let last = vec![dest_x];
-            let mut short_range = x_iter.chain(last.iter().cycle().copied());
-            result.extend(y_iter.map(|y| {
-                counter += 1.0;
-                while counter > spacing {
-                    x = short_range.next().expect("iterator should be endless");
-                    counter -= spacing;
-                }
-                Self::new(x, y)
-            }));
-        };
-        result
+        if len == 0 {
+            return vec![self];
+        }
+        // Step one cell along the longer axis, rounding the shorter one to the nearest cell.
+        #[allow(clippy::integer_division)]
+        let along = |delta: i64, step: i64| (delta * step * 2 + len * delta.signum()) / (len * 2);
+        (0..=len)
+            .map(|step| Self::new(self.x + along(delta_x, step), self.y + along(delta_y, step)))
+            .collect()
     }
 }
 
@@ -93,3 +55,51 @@ impl AddAssign for Position {
         self.y += rhs.y;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_line(start: Position, end: Position) {
+        let line = start.straight_line(end);
+        assert_eq!(line.first(), Some(&start), "{start:?} to {end:?}");
+        assert_eq!(line.last(), Some(&end), "{start:?} to {end:?}");
+        for pair in line.windows(2) {
+            if let [from, to] = *pair {
+                let (dx, dy) = ((to.x - from.x).abs(), (to.y - from.y).abs());
+                assert!(dx <= 1 && dy <= 1 && dx + dy > 0, "{from:?} to {to:?}");
+            }
+        }
+        assert_eq!(
+            line.len(),
+            usize::try_from((end.x - start.x).abs().max((end.y - start.y).abs()) + 1)
+                .unwrap_or_default()
+        );
+    }
+
+    #[test]
+    fn diagonal() {
+        check_line(Position::new(0, 0), Position::new(5, 5));
+        check_line(Position::new(3, -2), Position::new(-4, 5));
+        check_line(Position::new(0, 0), Position::new(1, -1));
+    }
+
+    #[test]
+    fn steep() {
+        check_line(Position::new(0, 0), Position::new(2, 9));
+        check_line(Position::new(4, 4), Position::new(3, -6));
+    }
+
+    #[test]
+    fn shallow() {
+        check_line(Position::new(0, 0), Position::new(9, 2));
+        check_line(Position::new(-3, 1), Position::new(-12, 0));
+        check_line(Position::new(0, 0), Position::new(7, 0));
+    }
+
+    #[test]
+    fn zero_length() {
+        let start = Position::new(2, 3);
+        assert_eq!(start.straight_line(start), vec![start]);
+    }
+}
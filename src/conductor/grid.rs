@@ -0,0 +1,173 @@
+use crate::automata::material::MaterialRegistry;
+use crate::automata::Automata;
+use crate::common::Position;
+use hecs::Entity;
+use pixelbuffer::Resolution;
+
+// Temperature empty cells settle back to, in degrees Celsius.
+pub const AMBIENT: f32 = 20.0;
+const AIR_CONDUCTIVITY: f32 = 0.02;
+// Fraction of the difference to ambient an empty cell loses every tick.
+const AIR_COOLING: f32 = 0.02;
+
+/// A single cell of the simulation, either empty or holding one automaton.
+#[derive(Clone, Copy, Debug)]
+pub struct Cell {
+    pub material: Option<Automata>,
+    pub entity: Option<Entity>,
+    // Rolled once when the cell is filled, so the color does not flicker every frame.
+    pub shade: u8,
+    pub temperature: f32,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            material: None,
+            entity: None,
+            shade: 0,
+            temperature: AMBIENT,
+        }
+    }
+}
+
+impl Cell {
+    pub const fn is_empty(&self) -> bool {
+        self.material.is_none()
+    }
+}
+
+/// Occupancy map of the world, the single source of truth for collisions.
+pub struct Grid {
+    resolution: Resolution,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    pub fn new(resolution: Resolution) -> Self {
+        Self {
+            resolution,
+            cells: vec![Cell::default(); resolution.area()],
+        }
+    }
+
+    pub fn contains(&self, pos: Position) -> bool {
+        pos.x >= 0
+            && pos.y >= 0
+            && pos.x < i64::from(self.resolution.width)
+            && pos.y < i64::from(self.resolution.height)
+    }
+
+    fn index(&self, pos: Position) -> Option<usize> {
+        self.contains(pos)
+            .then(|| pos.x as usize + pos.y as usize * usize::from(self.resolution.width))
+    }
+
+    pub fn get(&self, pos: Position) -> Option<&Cell> {
+        self.index(pos).and_then(|index| self.cells.get(index))
+    }
+
+    pub fn get_mut(&mut self, pos: Position) -> Option<&mut Cell> {
+        self.index(pos).and_then(|index| self.cells.get_mut(index))
+    }
+
+    // Returns true only for empty cells inside the grid, the border acts as a wall.
+    pub fn free(&self, pos: Position) -> bool {
+        self.get(pos).map_or(false, Cell::is_empty)
+    }
+
+    // True if `mover` could step into `pos`, either because it is empty or by displacing its content.
+    pub fn can_enter(&self, pos: Position, mover: Automata, materials: &MaterialRegistry) -> bool {
+        match self.get(pos).map(|cell| cell.material) {
+            Some(None) => true,
+            Some(Some(occupant)) => materials.get(mover).displaces(materials.get(occupant)),
+            None => false,
+        }
+    }
+
+    pub fn fill(
+        &mut self,
+        pos: Position,
+        material: Automata,
+        entity: Option<Entity>,
+        shade: u8,
+        temperature: f32,
+    ) {
+        if let Some(cell) = self.get_mut(pos) {
+            *cell = Cell {
+                material: Some(material),
+                entity,
+                shade,
+                temperature,
+            };
+        }
+    }
+
+    pub fn clear(&mut self, pos: Position) -> Cell {
+        self.get_mut(pos).map(std::mem::take).unwrap_or_default()
+    }
+
+    // Moves the content of `from` into `to`, leaving `from` empty.
+    pub fn move_cell(&mut self, from: Position, to: Position) {
+        let cell = self.clear(from);
+        if let Some(target) = self.get_mut(to) {
+            *target = cell;
+        }
+    }
+
+    pub fn swap(&mut self, a: Position, b: Position) {
+        if let (Some(a), Some(b)) = (self.index(a), self.index(b)) {
+            self.cells.swap(a, b);
+        }
+    }
+
+    // Exchanges heat between orthogonal neighbours, limited by the worse conductor of each pair.
+    pub fn conduct_heat(&mut self, materials: &MaterialRegistry) {
+        let width = usize::from(self.resolution.width);
+        let conductivity: Vec<f32> = self
+            .cells
+            .iter()
+            .map(|cell| {
+                cell.material
+                    .map_or(AIR_CONDUCTIVITY, |m| materials.get(m).conductivity)
+            })
+            .collect();
+        let heat = |index: usize| {
+            self.cells
+                .get(index)
+                .map(|cell| cell.temperature)
+                .zip(conductivity.get(index).copied())
+        };
+        let next: Vec<f32> = (0..self.cells.len())
+            .map(|index| {
+                let (temperature, here) = heat(index).unwrap_or((AMBIENT, 0.0));
+                let left = (index % width != 0).then(|| index - 1);
+                let right = ((index + 1) % width != 0).then(|| index + 1);
+                let up = index.checked_sub(width);
+                let down = Some(index + width);
+                let flow: f32 = [left, right, up, down]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(heat)
+                    .map(|(other, there)| here.min(there) * 0.25 * (other - temperature))
+                    .sum();
+                temperature + flow
+            })
+            .collect();
+        for (cell, temperature) in self.cells.iter_mut().zip(next) {
+            cell.temperature = temperature;
+            if cell.is_empty() {
+                cell.temperature += (AMBIENT - temperature) * AIR_COOLING;
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Position, &Cell)> {
+        let width = usize::from(self.resolution.width);
+        self.cells.iter().enumerate().map(move |(index, cell)| {
+            #[allow(clippy::integer_division)]
+            let pos = Position::new((index % width) as i64, (index / width) as i64);
+            (pos, cell)
+        })
+    }
+}
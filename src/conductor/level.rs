@@ -0,0 +1,273 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use crate::automata::material::{parse_rgb, MaterialRegistry};
+use crate::automata::Automata;
+use crate::common::Position;
+use pixelbuffer::{ImageError, PixelBuffer, Resolution};
+
+// Shipped with the binary, used when there is no palette file next to it.
+const DEFAULT_PALETTE: &str = include_str!("../../palette.txt");
+// Unknown colors listed in an error before the rest are only counted.
+const REPORTED_COLORS: usize = 10;
+
+/// A color in a level image that isn't in the palette, with the first pixel using it.
+#[derive(Debug)]
+pub struct UnknownColor {
+    pub color: [u8; 3],
+    pub x: u16,
+    pub y: u16,
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(std::io::Error),
+    Image(ImageError),
+    Palette { line: usize, message: String },
+    UnknownColors(Vec<UnknownColor>),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Io(ref err) => write!(f, "couldn't read the palette: {err}"),
+            Self::Image(ref err) => write!(f, "{err}"),
+            Self::Palette { line, ref message } => write!(f, "palette line {line}: {message}"),
+            Self::UnknownColors(ref colors) => {
+                write!(f, "colors missing from the palette:")?;
+                for (index, unknown) in colors.iter().take(REPORTED_COLORS).enumerate() {
+                    let separator = if index == 0 { " " } else { ", " };
+                    let [r, g, b] = unknown.color;
+                    write!(
+                        f,
+                        "{separator}{r} {g} {b} at ({}, {})",
+                        unknown.x, unknown.y
+                    )?;
+                }
+                if colors.len() > REPORTED_COLORS {
+                    write!(f, " and {} more", colors.len() - REPORTED_COLORS)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<std::io::Error> for LevelError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ImageError> for LevelError {
+    fn from(err: ImageError) -> Self {
+        Self::Image(err)
+    }
+}
+
+/// Mapping from colors in a level image to materials, `None` for empty cells.
+pub struct Palette {
+    entries: Vec<([u8; 3], Option<Automata>)>,
+}
+
+impl Palette {
+    /// Reads the palette from `path`, falling back to the built-in one if it doesn't exist.
+    pub fn load(path: impl AsRef<Path>, materials: &MaterialRegistry) -> Result<Self, LevelError> {
+        match std::fs::read_to_string(path) {
+            Ok(definitions) => Self::parse(&definitions, materials),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Self::parse(DEFAULT_PALETTE, materials)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn parse(definitions: &str, materials: &MaterialRegistry) -> Result<Self, LevelError> {
+        let mut entries = Vec::new();
+        for (index, line) in definitions.lines().enumerate() {
+            let error = |message: String| LevelError::Palette {
+                line: index + 1,
+                message,
+            };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (color, name) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected `r g b = material`, got {line}")))?;
+            let color = parse_rgb(color.trim()).map_err(error)?;
+            let material = match name.trim() {
+                "empty" => None,
+                name => Some(
+                    materials
+                        .find(name)
+                        .ok_or_else(|| error(format!("unknown material {name}")))?,
+                ),
+            };
+            entries.push((color, material));
+        }
+        Ok(Self { entries })
+    }
+
+    fn entry(&self, color: [u8; 3]) -> Option<&([u8; 3], Option<Automata>)> {
+        self.entries.iter().find(|entry| entry.0 == color)
+    }
+}
+
+/// Automata placed by a level image, ready to be spawned into a `World`.
+pub struct Level {
+    pub automata: Vec<(Position, Automata)>,
+}
+
+impl Level {
+    /// Maps every cell of a world with the given resolution to a pixel of `image`,
+    /// stretching the image if its size differs.
+    pub fn from_image(
+        image: &PixelBuffer,
+        palette: &Palette,
+        resolution: Resolution,
+    ) -> Result<Self, LevelError> {
+        let (width, height) = resolution.into();
+        let (image_width, image_height) = image.resolution().into();
+        let mut automata = Vec::new();
+        let mut unknown: Vec<UnknownColor> = Vec::new();
+        let mut reported: HashSet<[u8; 3]> = HashSet::new();
+        for y in 0..height {
+            for x in 0..width {
+                #[allow(clippy::integer_division)]
+                let (image_x, image_y) = (
+                    (u32::from(x) * u32::from(image_width) / u32::from(width)) as u16,
+                    (u32::from(y) * u32::from(image_height) / u32::from(height)) as u16,
+                );
+                let color: [u8; 3] = match image.get_pixel((image_x, image_y).into()) {
+                    Some(pixel) => pixel.into(),
+                    None => continue,
+                };
+                match palette.entry(color) {
+                    Some(&(_, Some(material))) => {
+                        automata.push((Position::new(x.into(), y.into()), material));
+                    }
+                    Some(&(_, None)) => (),
+                    None if !reported.insert(color) => (),
+                    None => unknown.push(UnknownColor {
+                        color,
+                        x: image_x,
+                        y: image_y,
+                    }),
+                }
+            }
+        }
+        if unknown.is_empty() {
+            Ok(Self { automata })
+        } else {
+            Err(LevelError::UnknownColors(unknown))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pixelbuffer::Pixel;
+
+    fn materials() -> MaterialRegistry {
+        MaterialRegistry::parse(include_str!("../../materials.txt"))
+            .expect("the shipped materials are valid")
+    }
+
+    fn palette_error(definitions: &str) -> (usize, String) {
+        match Palette::parse(definitions, &materials()) {
+            Err(LevelError::Palette { line, message }) => (line, message),
+            Err(err) => panic!("expected a palette error, got {err}"),
+            Ok(_) => panic!("expected a palette error for {definitions:?}"),
+        }
+    }
+
+    #[test]
+    fn shipped_palette() {
+        let materials = materials();
+        let palette = Palette::parse(DEFAULT_PALETTE, &materials).expect("the palette is valid");
+        let sand = materials.find("sand");
+        assert_eq!(
+            palette.entry([120, 90, 70]).map(|entry| entry.1),
+            Some(sand)
+        );
+        assert_eq!(palette.entry([0, 0, 0]).map(|entry| entry.1), Some(None));
+        assert!(palette.entry([1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn palette_errors() {
+        let (line, message) = palette_error("# sand\n\n1 2 3 sand");
+        assert_eq!(line, 3);
+        assert!(message.contains("expected `r g b = material`"), "{message}");
+        let (line, message) = palette_error("1 2 = sand");
+        assert_eq!(line, 1);
+        assert!(message.contains("three channels"), "{message}");
+        let (_, message) = palette_error("1 2 300 = sand");
+        assert!(message.contains("invalid color"), "{message}");
+        let (_, message) = palette_error("1 2 3 = quicksand");
+        assert!(message.contains("unknown material quicksand"), "{message}");
+    }
+
+    #[test]
+    fn image_to_level() {
+        let materials = materials();
+        let palette = Palette::parse("1 1 1 = sand\n0 0 0 = empty", &materials)
+            .expect("the palette is valid");
+        let mut image = PixelBuffer::new(Resolution::new(2, 2));
+        image.set_pixel((0, 0).into(), Pixel::black());
+        image.set_pixel((1, 0).into(), Pixel::new(1, 1, 1));
+        image.set_pixel((0, 1).into(), Pixel::new(1, 1, 1));
+        image.set_pixel((1, 1).into(), Pixel::black());
+        // Twice the size, so every image pixel covers two by two cells.
+        let level = Level::from_image(&image, &palette, Resolution::new(4, 4))
+            .expect("every color is in the palette");
+        let sand = materials.find("sand").expect("sand is defined");
+        let mut cells: Vec<(i64, i64)> = level
+            .automata
+            .iter()
+            .map(|&(pos, material)| {
+                assert_eq!(material, sand);
+                (pos.x, pos.y)
+            })
+            .collect();
+        cells.sort_unstable();
+        assert_eq!(
+            cells,
+            vec![
+                (0, 2),
+                (0, 3),
+                (1, 2),
+                (1, 3),
+                (2, 0),
+                (2, 1),
+                (3, 0),
+                (3, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_colors_are_reported_once_with_their_first_pixel() {
+        let palette = Palette::parse("0 0 0 = empty", &materials()).expect("the palette is valid");
+        let mut image = PixelBuffer::new(Resolution::new(3, 2));
+        for (x, y) in [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)] {
+            image.set_pixel((x, y).into(), Pixel::black());
+        }
+        image.set_pixel((2, 0).into(), Pixel::new(9, 9, 9));
+        image.set_pixel((1, 1).into(), Pixel::new(9, 9, 9));
+        image.set_pixel((0, 1).into(), Pixel::new(7, 8, 9));
+        let error = Level::from_image(&image, &palette, Resolution::new(3, 2))
+            .err()
+            .expect("the image has unknown colors");
+        assert_eq!(
+            error.to_string(),
+            "colors missing from the palette: 9 9 9 at (2, 0), 7 8 9 at (0, 1)"
+        );
+    }
+}
@@ -36,13 +36,100 @@
 mod world;
 use world::World;
 mod automata;
+mod brush;
 mod common;
-use pixelbuffer::Resolution;
+mod grid;
+mod level;
+mod snapshot;
+use automata::material::MaterialRegistry;
+use level::{Level, Palette};
+use pixelbuffer::{InputLog, InputRecorder, Pixel, Resolution};
+use std::fmt::Display;
+use std::str::FromStr;
 
-const SCALE: u16 = 1;
-const RESOLUTION: Resolution = Resolution::new(240 * SCALE, 240 * SCALE);
+const RESOLUTION: Resolution = Resolution::new(240, 240);
+// Screen pixels per cell along each axis.
+const SCALE: u16 = 4;
+const MATERIALS: &str = "materials.txt";
+const PALETTE: &str = "palette.txt";
 
 fn main() {
-    let mut world = World::new(RESOLUTION, "game");
-    world.start();
+    let materials = MaterialRegistry::load(MATERIALS)
+        .unwrap_or_else(|err| panic!("Couldn't load {MATERIALS}: {err}"));
+    // `--headless <ticks>` runs the simulation without a window, for machines without a display.
+    // `--output <image>` saves the last headless frame as a PNG, or a PPM by its extension.
+    // `--seed <seed>` replays a run, the seed of every run is printed at startup.
+    // `--level <image>` starts from a PNG or PPM level, colors are mapped by palette.txt.
+    // `--record-input <file>` logs every frame's input, `--replay <file>` plays such a log back.
+    let mut headless: Option<u64> = None;
+    let mut output: Option<String> = None;
+    let mut seed = None;
+    let mut level: Option<String> = None;
+    let mut record: Option<String> = None;
+    let mut replay: Option<String> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => headless = Some(parse_arg(&arg, args.next())),
+            "--output" => output = Some(parse_arg(&arg, args.next())),
+            "--seed" => seed = Some(parse_arg(&arg, args.next())),
+            "--level" => level = Some(parse_arg(&arg, args.next())),
+            "--record-input" => record = Some(parse_arg(&arg, args.next())),
+            "--replay" => replay = Some(parse_arg(&arg, args.next())),
+            _ => panic!("Unknown argument {arg}"),
+        }
+    }
+    let replay = replay.map(|path| {
+        InputLog::read(&path).unwrap_or_else(|err| panic!("Couldn't read {path}: {err}"))
+    });
+    // A replay is only faithful with the seed it was recorded with.
+    let seed = seed
+        .or_else(|| replay.as_ref().map(InputLog::seed))
+        .unwrap_or_else(|| fastrand::u64(..));
+    println!("Seed {seed}");
+    let record = record.map(|path| {
+        InputRecorder::create(&path, seed)
+            .unwrap_or_else(|err| panic!("Couldn't create {path}: {err}"))
+    });
+    let level = level.map(|path| {
+        let palette = Palette::load(PALETTE, &materials)
+            .unwrap_or_else(|err| panic!("Couldn't load {PALETTE}: {err}"));
+        pixelbuffer::read_image(&path)
+            .map_err(Into::into)
+            .and_then(|image| Level::from_image(&image, &palette, RESOLUTION))
+            .unwrap_or_else(|err| panic!("Couldn't load level {path}: {err}"))
+    });
+    let mut world = World::new(RESOLUTION, materials, seed);
+    if let Some(level) = level {
+        world.load_level(&level);
+    }
+    match headless {
+        Some(ticks) => {
+            let ticks = ticks.max(replay.as_ref().map_or(0, InputLog::frames));
+            let frame = world.run_headless(ticks, replay);
+            let drawn = frame
+                .pixels()
+                .iter()
+                .filter(|&&pixel| pixel != Pixel::black())
+                .count();
+            println!("Ran {ticks} ticks headless, {drawn} pixels drawn");
+            if let Some(path) = output {
+                pixelbuffer::write_image(&frame, &path)
+                    .unwrap_or_else(|err| panic!("Couldn't write {path}: {err}"));
+                println!("Saved the last frame to {path}");
+            }
+        }
+        None if output.is_some() => panic!("--output needs --headless"),
+        None => world.start("game", SCALE, replay, record),
+    }
+}
+
+fn parse_arg<T: FromStr>(name: &str, value: Option<String>) -> T
+where
+    T::Err: Display,
+{
+    let value = value.unwrap_or_else(|| panic!("Missing value for {name}"));
+    value
+        .parse()
+        .unwrap_or_else(|err| panic!("Invalid value {value} for {name}: {err}"))
 }
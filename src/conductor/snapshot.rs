@@ -0,0 +1,295 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use pixelbuffer::Resolution;
+
+// Bumped whenever the layout below changes, files from other versions are rejected.
+pub const VERSION: u16 = 1;
+const MAGIC: &[u8; 4] = b"SAND";
+
+const HAS_VELOCITY: u8 = 1;
+const HAS_LIFETIME: u8 = 2;
+
+/// Saved state of a single grid cell and the automaton in it.
+pub struct SavedCell {
+    // Index into `Snapshot::materials`, `None` for empty cells.
+    pub material: Option<u16>,
+    pub shade: u8,
+    pub temperature: f32,
+    pub velocity: Option<f32>,
+    pub lifetime: Option<u32>,
+}
+
+/// Full state of a world, stored as a versioned little-endian binary file.
+///
+/// Layout: magic, version, width, height, rng state, material names, selection,
+/// then every cell in row order.
+pub struct Snapshot {
+    pub width: u16,
+    pub height: u16,
+    pub rng: u64,
+    // Material ids are stored by name, so reordering the definitions doesn't break old saves.
+    pub materials: Vec<String>,
+    pub selection: u16,
+    pub cells: Vec<SavedCell>,
+}
+
+impl Snapshot {
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        writer.write_all(&self.rng.to_le_bytes())?;
+        writer.write_all(&(self.materials.len() as u16).to_le_bytes())?;
+        for name in &self.materials {
+            writer.write_all(&(name.len() as u16).to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+        }
+        writer.write_all(&self.selection.to_le_bytes())?;
+        for cell in &self.cells {
+            // 0 marks an empty cell, anything else is the material index plus one.
+            let material = cell.material.map_or(0, |index| index + 1);
+            writer.write_all(&material.to_le_bytes())?;
+            writer.write_all(&cell.temperature.to_le_bytes())?;
+            if cell.material.is_none() {
+                continue;
+            }
+            let mut flags = 0;
+            if cell.velocity.is_some() {
+                flags |= HAS_VELOCITY;
+            }
+            if cell.lifetime.is_some() {
+                flags |= HAS_LIFETIME;
+            }
+            writer.write_all(&[cell.shade, flags])?;
+            if let Some(velocity) = cell.velocity {
+                writer.write_all(&velocity.to_le_bytes())?;
+            }
+            if let Some(lifetime) = cell.lifetime {
+                writer.write_all(&lifetime.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a snapshot of a world with the `expected` resolution, rejecting others before
+    /// any cells are read.
+    pub fn read(reader: &mut impl Read, expected: Resolution) -> Result<Self, SnapshotError> {
+        if &read_bytes::<4>(reader)? != MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        let version = u16::from_le_bytes(read_bytes(reader)?);
+        if version != VERSION {
+            return Err(SnapshotError::Version(version));
+        }
+        let width = u16::from_le_bytes(read_bytes(reader)?);
+        let height = u16::from_le_bytes(read_bytes(reader)?);
+        if (width, height) != expected.into() {
+            return Err(SnapshotError::Resolution {
+                expected: expected.into(),
+                found: (width, height),
+            });
+        }
+        let rng = u64::from_le_bytes(read_bytes(reader)?);
+        let count = u16::from_le_bytes(read_bytes(reader)?);
+        let mut materials = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            let len = u16::from_le_bytes(read_bytes(reader)?);
+            let mut name = vec![0; usize::from(len)];
+            reader.read_exact(&mut name)?;
+            let name = String::from_utf8(name)
+                .map_err(|_| SnapshotError::Corrupt("material name is not valid UTF-8"))?;
+            materials.push(name);
+        }
+        let selection = u16::from_le_bytes(read_bytes(reader)?);
+        if selection >= count {
+            return Err(SnapshotError::Corrupt("selection is not a saved material"));
+        }
+        let area = usize::from(width) * usize::from(height);
+        let mut cells = Vec::with_capacity(area);
+        for _ in 0..area {
+            let material = u16::from_le_bytes(read_bytes(reader)?).checked_sub(1);
+            let temperature = f32::from_le_bytes(read_bytes(reader)?);
+            let mut cell = SavedCell {
+                material,
+                shade: 0,
+                temperature,
+                velocity: None,
+                lifetime: None,
+            };
+            if let Some(material) = material {
+                if material >= count {
+                    return Err(SnapshotError::Corrupt("cell refers to an unknown material"));
+                }
+                let [shade, flags] = read_bytes(reader)?;
+                cell.shade = shade;
+                if flags & HAS_VELOCITY != 0 {
+                    cell.velocity = Some(f32::from_le_bytes(read_bytes(reader)?));
+                }
+                if flags & HAS_LIFETIME != 0 {
+                    cell.lifetime = Some(u32::from_le_bytes(read_bytes(reader)?));
+                }
+            }
+            cells.push(cell);
+        }
+        Ok(Self {
+            width,
+            height,
+            rng,
+            materials,
+            selection,
+            cells,
+        })
+    }
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    NotASnapshot,
+    Truncated,
+    Version(u16),
+    Resolution {
+        expected: (u16, u16),
+        found: (u16, u16),
+    },
+    UnknownMaterial(String),
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Io(ref err) => write!(f, "couldn't access the snapshot: {err}"),
+            Self::NotASnapshot => write!(f, "not a snapshot file"),
+            Self::Truncated => write!(f, "the snapshot ends unexpectedly"),
+            Self::Version(version) => write!(
+                f,
+                "snapshot has version {version}, but only version {VERSION} is supported"
+            ),
+            Self::Resolution { expected, found } => write!(
+                f,
+                "snapshot is {}x{}, but the world is {}x{}",
+                found.0, found.1, expected.0, expected.1
+            ),
+            Self::UnknownMaterial(ref name) => {
+                write!(f, "snapshot uses material {name}, which is not defined")
+            }
+            Self::Corrupt(reason) => write!(f, "corrupt snapshot: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            Self::Truncated
+        } else {
+            Self::Io(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(width: u16, height: u16) -> Vec<u8> {
+        let cells = (0..usize::from(width) * usize::from(height))
+            .map(|index| SavedCell {
+                material: (index % 2 == 0).then_some(0),
+                shade: 3,
+                temperature: 20.0,
+                velocity: Some(1.5),
+                lifetime: None,
+            })
+            .collect();
+        let snapshot = Snapshot {
+            width,
+            height,
+            rng: 7,
+            materials: vec!["sand".to_owned()],
+            selection: 0,
+            cells,
+        };
+        let mut bytes = Vec::new();
+        snapshot
+            .write(&mut bytes)
+            .expect("writing to memory can't fail");
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = snapshot(4, 3);
+        let read = Snapshot::read(&mut bytes.as_slice(), Resolution::new(4, 3))
+            .expect("a written snapshot reads back");
+        assert_eq!(read.cells.len(), 12);
+        assert_eq!(read.rng, 7);
+        assert_eq!(read.cells.first().and_then(|cell| cell.velocity), Some(1.5));
+    }
+
+    #[test]
+    fn truncated_stream() {
+        let bytes = snapshot(4, 3);
+        for len in [0, 3, 10, bytes.len() - 1] {
+            let result = Snapshot::read(
+                &mut bytes.get(..len).unwrap_or_default(),
+                Resolution::new(4, 3),
+            );
+            assert!(
+                matches!(result, Err(SnapshotError::Truncated)),
+                "{len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn other_version() {
+        let mut bytes = snapshot(4, 3);
+        bytes.splice(4..6, (VERSION + 1).to_le_bytes());
+        let error = Snapshot::read(&mut bytes.as_slice(), Resolution::new(4, 3))
+            .err()
+            .expect("other versions are rejected");
+        assert!(matches!(error, SnapshotError::Version(version) if version == VERSION + 1));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "snapshot has version {}, but only version {VERSION} is supported",
+                VERSION + 1
+            )
+        );
+    }
+
+    #[test]
+    fn not_a_snapshot() {
+        let mut bytes = snapshot(4, 3);
+        bytes.splice(0..4, *b"PNG ");
+        let result = Snapshot::read(&mut bytes.as_slice(), Resolution::new(4, 3));
+        assert!(matches!(result, Err(SnapshotError::NotASnapshot)));
+    }
+
+    #[test]
+    fn wrong_size_header() {
+        let mut bytes = snapshot(4, 3);
+        // Claims the largest possible world, which must be rejected before reading any cells.
+        bytes.splice(6..10, [0xff; 4]);
+        let result = Snapshot::read(&mut bytes.as_slice(), Resolution::new(4, 3));
+        assert!(matches!(
+            result,
+            Err(SnapshotError::Resolution {
+                found: (u16::MAX, u16::MAX),
+                ..
+            })
+        ));
+    }
+}
@@ -1,95 +1,525 @@
-use crate::automata;
-use crate::automata::{Automata, Destination};
+use crate::automata::material::{Material, MaterialRegistry, Phase, Rule};
+use crate::automata::{
+    Automata, Intent, Lifetime, Neighbourhood, Velocity, GRAVITY, TERMINAL_VELOCITY,
+};
+use crate::brush::{Brush, Mode, Shape, Tool};
 use crate::common::Position;
-use hecs::PreparedQuery;
+use crate::grid::Grid;
+use crate::level::Level;
+use crate::snapshot::{SavedCell, Snapshot, SnapshotError};
+use fastrand::Rng;
+use glfw::Action;
 use hecs::World as Ecs;
-use pixelbuffer::{Event, Pixel, PixelBuffer, Resolution, Window};
+use hecs::{Entity, EntityBuilder, PreparedQuery};
+use pixelbuffer::{
+    Event, InputLog, InputRecorder, Pixel, PixelBuffer, Presenter, Recorder, Resolution, TextStyle,
+    Window,
+};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Where the quick-save and quick-load keys keep their snapshot.
+const QUICKSAVE: &str = "quicksave.sand";
+// Key bindings listed by the overlay, toggled with H.
+const HELP: &str = "A/D cycle  W S F Space pick
+LMB paint  RMB erase
+[ ] or wheel  brush size
+Q shape  E spray  - = density
+F5 save  F9 load  F12 shot
+R gif  P png  F11 fullscreen
+H hide help  Esc quit";
+
 pub struct World {
-    window: Window,
     resolution: Resolution,
 
     ecs: Ecs,
+    grid: Grid,
+    materials: MaterialRegistry,
     mouse: (f64, f64),
     selection: Automata,
+    brush: Brush,
+    // The tool whose button is held, and where the brush was applied last tick.
+    stroke: Option<Tool>,
+    last_stroke: Option<Position>,
+    // Entities to remove at the end of the tick, any system can add to it.
+    despawn_queue: Vec<Entity>,
+    // Every random choice in the simulation goes through this, so a seed reproduces a run.
+    rng: Rng,
+    // Whether the key bindings are listed on screen.
+    help: bool,
 }
 
 impl World {
-    pub fn new(resolution: Resolution, title: &str) -> Self {
+    /// Creates an empty world, nothing is shown until `start` opens a window for it.
+    /// Worlds with the same seed and the same input evolve identically.
+    pub fn new(resolution: Resolution, materials: MaterialRegistry, seed: u64) -> Self {
+        let selection = materials
+            .find("sand")
+            .unwrap_or_else(|| Automata::from_index(0));
         Self {
-            window: Window::new(resolution, title),
             resolution,
             ecs: Ecs::new(),
+            grid: Grid::new(resolution),
+            materials,
             mouse: (0.0, 0.0),
-            selection: Automata::Sand,
+            selection,
+            brush: Brush::default(),
+            stroke: None,
+            last_stroke: None,
+            despawn_queue: Vec::new(),
+            rng: Rng::with_seed(seed),
+            help: false,
         }
     }
 
-    fn add_walkers(&mut self, pos: Position) {
-        let to_spawn = (0..50).map(|_| (self.selection, pos, Destination::from(pos)));
-        self.ecs.spawn_batch(to_spawn);
+    // Applies the brush along the path the cursor took since the last tick, while a button is held.
+    fn paint_system(&mut self) {
+        let Some(tool) = self.stroke else {
+            return;
+        };
+        let cursor = Position::new(self.mouse.0 as i64, self.mouse.1 as i64);
+        let from = self.last_stroke.unwrap_or(cursor);
+        for pos in self.brush.stroke(from, cursor) {
+            if self.brush.mode == Mode::Spray && self.rng.u8(0..100) >= self.brush.density {
+                continue;
+            }
+            match tool {
+                Tool::Paint if self.grid.free(pos) => {
+                    self.spawn_automaton(pos, self.selection);
+                }
+                Tool::Paint => (),
+                Tool::Erase => match self.grid.get(pos).and_then(|cell| cell.entity) {
+                    Some(entity) => self.queue_despawn(entity),
+                    // Static cells have no entity, there is nothing to wait for.
+                    None => {
+                        self.grid.clear(pos);
+                    }
+                },
+            }
+        }
+        self.last_stroke = Some(cursor);
     }
 
-    fn update_automata_destination_system(
-        &mut self,
-        query: &mut PreparedQuery<(&mut Destination, &Position, &Automata)>,
-    ) {
-        for (_id, (dest, pos, automata)) in query.query_mut(&mut self.ecs) {
-            match automata {
-                Automata::RandomWalker => *dest = automata::random_walker::update(pos),
-                Automata::Water => *dest = automata::water::update(pos),
-                Automata::Sand => *dest = automata::sand::update(pos),
+    // Static materials only take up their cell, everything else gets an entity to simulate it.
+    fn spawn_automaton(&mut self, pos: Position, material: Automata) -> Option<Entity> {
+        let Material {
+            lifetime,
+            phase,
+            rule,
+            temperature,
+            ..
+        } = *self.materials.get(material);
+        if rule == Rule::Static {
+            self.grid
+                .fill(pos, material, None, self.rng.u8(..), temperature);
+            return None;
+        }
+        let mut builder = EntityBuilder::new();
+        builder.add(material).add(pos);
+        if phase != Phase::Gas {
+            builder.add(Velocity::default());
+        }
+        if lifetime > 0 {
+            builder.add(Lifetime(self.rng.u32(lifetime..lifetime.saturating_mul(2))));
+        }
+        let entity = self.ecs.spawn(builder.build());
+        self.grid
+            .fill(pos, material, Some(entity), self.rng.u8(..), temperature);
+        Some(entity)
+    }
+
+    // Swaps whatever automaton is at `pos` for a fresh one of another material.
+    fn replace_automaton(&mut self, pos: Position, into: Automata) {
+        let cell = self.grid.clear(pos);
+        if cell.is_empty() {
+            return;
+        }
+        if let Some(entity) = cell.entity {
+            if self.ecs.despawn(entity).is_err() {
+                return;
             }
         }
+        self.spawn_automaton(pos, into);
+    }
+
+    fn select(&mut self, name: &str) {
+        match self.materials.find(name) {
+            Some(material) => self.selection = material,
+            None => println!("No material named {name}"),
+        }
+    }
+
+    fn cycle_selection(&mut self, step: i64) {
+        let count = self.materials.len() as i64;
+        let index = (self.selection.index() as i64 + step).rem_euclid(count);
+        self.selection = Automata::from_index(index as usize);
+    }
+
+    fn set_position(&mut self, entity: Entity, pos: Position) {
+        if let Ok(mut position) = self.ecs.get_mut::<Position>(entity) {
+            *position = pos;
+        }
     }
 
-    fn resolve_movement_and_draw_point_automata_system(
+    fn update_automata_system(
         &mut self,
-        buffer: &mut PixelBuffer,
-        query: &mut PreparedQuery<(&mut Position, &Destination, &Automata)>,
-    ) {
-        for (_id, (pos, dest, automata)) in query.query_mut(&mut self.ecs) {
-            if (dest.x < 0
-                || dest.y < 0
-                || dest.x >= i64::from(self.resolution.width)
-                || dest.y >= i64::from(self.resolution.height))
-                && (pos.x < 0
-                    || pos.y < 0
-                    || pos.x >= i64::from(self.resolution.width)
-                    || pos.y >= i64::from(self.resolution.height))
-            {
-                continue;
+        query: &mut PreparedQuery<(&Position, &Automata)>,
+    ) -> Vec<(Entity, Intent)> {
+        let mut intents = Vec::new();
+        let mut wanted = Vec::new();
+        for (id, (pos, automata)) in query.query(&self.ecs).iter() {
+            let view = Neighbourhood::new(*pos, *automata, &self.grid, &self.materials, &self.ecs);
+            let rule = self.materials.get(*automata).rule;
+            rule.automaton().update(&view, &self.rng, &mut wanted);
+            intents.extend(wanted.drain(..).map(|intent| (id, intent)));
+        }
+        intents
+    }
+
+    // Moves the automaton along a line towards `pos + offset`, stopping at the first obstacle.
+    // With `displace`, an obstacle right next to it may be swapped with instead.
+    fn travel(&mut self, entity: Entity, pos: Position, offset: Position, displace: bool) {
+        let path = pos.straight_line(pos + offset);
+        let reached = path
+            .iter()
+            .skip(1)
+            .take_while(|step| self.grid.free(**step))
+            .last()
+            .copied();
+        if let Some(reached) = reached {
+            self.grid.move_cell(pos, reached);
+            self.set_position(entity, reached);
+            if reached != pos + offset {
+                self.stop(entity);
             }
+            return;
+        }
+        let first = match path.get(1) {
+            Some(first) if displace => *first,
+            _ => return self.stop(entity),
+        };
+        let mover = self.grid.get(pos).and_then(|cell| cell.material);
+        if !mover.map_or(false, |m| self.grid.can_enter(first, m, &self.materials)) {
+            return self.stop(entity);
+        }
+        let occupant = self.grid.get(first).and_then(|cell| cell.entity);
+        self.grid.swap(pos, first);
+        self.set_position(entity, first);
+        if let Some(occupant) = occupant {
+            self.set_position(occupant, pos);
+        }
+        // Pushing through something else slows the automaton down to a crawl.
+        if let Ok(mut velocity) = self.ecs.get_mut::<Velocity>(entity) {
+            velocity.y = velocity.y.min(1.0);
+        }
+    }
 
-            // Only if destination is far away:
-            /* let to_check = Position::new(dest.x, dest.y).straight_line(*pos);
-            let free = to_check
-                .iter()
-                .find(|pos| buffer.free((pos.x as u16, pos.y as u16).into()));
-                */
-            let mut dest = Position::from(*dest);
-            let free = buffer.free((dest.x as u16, dest.y as u16).into());
-            if !free {
-                dest = *pos
-                    .straight_line(Position::new(pos.x, pos.y - 100))
-                    .iter()
-                    .find(|pos| buffer.free((pos.x as u16, pos.y as u16).into()))
-                    .unwrap_or(pos);
-            }
-            *pos = dest;
-            let pixel = match automata {
-                Automata::RandomWalker => Pixel::new(170, fastrand::u8(180..220), 220),
-                Automata::Water => Pixel::new(100, 100, fastrand::u8(180..255)),
-                Automata::Sand => Pixel::new(fastrand::u8(120..200), 90, 70),
+    fn stop(&mut self, entity: Entity) {
+        if let Ok(mut velocity) = self.ecs.get_mut::<Velocity>(entity) {
+            *velocity = Velocity::default();
+        }
+    }
+
+    fn apply_intents_system(&mut self, intents: Vec<(Entity, Intent)>) {
+        for (entity, intent) in intents {
+            // Intents applied before this one may have moved or removed the automaton.
+            let pos = match self.ecs.get::<Position>(entity) {
+                Ok(pos) => *pos,
+                Err(_) => continue,
             };
-            buffer.set_pixel((dest.x as u16, dest.y as u16).into(), pixel);
+            match intent {
+                Intent::Move(offset) => self.travel(entity, pos, offset, false),
+                Intent::Swap(offset) => self.travel(entity, pos, offset, true),
+                Intent::Transform { offset, from, into } => {
+                    // Something else may have moved into the cell since the intent was made.
+                    if self.grid.get(pos + offset).and_then(|cell| cell.material) == Some(from) {
+                        self.replace_automaton(pos + offset, into);
+                    }
+                }
+                Intent::Spawn { offset, material } => {
+                    if self.grid.free(pos + offset) {
+                        self.spawn_automaton(pos + offset, material);
+                    }
+                }
+                Intent::Die => self.queue_despawn(entity),
+            }
+        }
+    }
+
+    fn gravity_system(&mut self, query: &mut PreparedQuery<(&mut Velocity, &Position)>) {
+        for (_id, (velocity, pos)) in query.query_mut(&mut self.ecs) {
+            if self.grid.free(*pos + Position::new(0, 1)) {
+                velocity.y = (velocity.y + GRAVITY).min(TERMINAL_VELOCITY);
+            } else {
+                *velocity = Velocity::default();
+            }
         }
     }
 
-    fn run_update_systems(&mut self, buffer: &mut PixelBuffer) {
-        let mut q = PreparedQuery::<(&mut Destination, &Position, &Automata)>::default();
-        self.update_automata_destination_system(&mut q);
-        let mut q = PreparedQuery::<(&mut Position, &Destination, &Automata)>::default();
-        self.resolve_movement_and_draw_point_automata_system(buffer, &mut q);
+    fn dissipate_automata_system(&mut self, query: &mut PreparedQuery<&mut Lifetime>) {
+        for (id, lifetime) in query.query_mut(&mut self.ecs) {
+            lifetime.0 = lifetime.0.saturating_sub(1);
+            if lifetime.0 == 0 {
+                self.despawn_queue.push(id);
+            }
+        }
+    }
+
+    fn queue_despawn(&mut self, entity: Entity) {
+        self.despawn_queue.push(entity);
+    }
+
+    // Removes every queued entity together with its cell.
+    fn remove_dead_system(&mut self) {
+        for entity in std::mem::take(&mut self.despawn_queue) {
+            let pos = match self.ecs.get::<Position>(entity) {
+                Ok(pos) => *pos,
+                Err(_) => continue,
+            };
+            // Something else may have been queued twice or replaced in the meantime.
+            if self.ecs.despawn(entity).is_ok()
+                && self.grid.get(pos).and_then(|cell| cell.entity) == Some(entity)
+            {
+                self.grid.clear(pos);
+            }
+        }
+    }
+
+    fn heat_system(&mut self) {
+        self.grid.conduct_heat(&self.materials);
+        let changes: Vec<(Position, Automata, f32)> = self
+            .grid
+            .iter()
+            .filter_map(|(pos, cell)| {
+                let material = self.materials.get(cell.material?);
+                let into = material.phase_change(cell.temperature)?;
+                Some((pos, into, cell.temperature))
+            })
+            .collect();
+        for (pos, into, temperature) in changes {
+            self.replace_automaton(pos, into);
+            // The new phase keeps the heat of the old one.
+            if let Some(cell) = self.grid.get_mut(pos) {
+                cell.temperature = temperature;
+            }
+        }
+    }
+
+    fn run_update_systems(&mut self) {
+        let mut q = PreparedQuery::<(&mut Velocity, &Position)>::default();
+        self.gravity_system(&mut q);
+        let mut q = PreparedQuery::<(&Position, &Automata)>::default();
+        let intents = self.update_automata_system(&mut q);
+        self.apply_intents_system(intents);
+        self.heat_system();
+        let mut q = PreparedQuery::<&mut Lifetime>::default();
+        self.dissipate_automata_system(&mut q);
+        self.remove_dead_system();
+    }
+
+    fn begin_stroke(&mut self, tool: Tool) {
+        self.stroke = Some(tool);
+        self.last_stroke = None;
+    }
+
+    fn end_stroke(&mut self, tool: Tool) {
+        if self.stroke == Some(tool) {
+            self.stroke = None;
+        }
+    }
+
+    /// Advances the simulation by one tick without touching the window.
+    pub fn step(&mut self) {
+        self.paint_system();
+        self.run_update_systems();
+    }
+
+    /// Renders the current state of the grid into a fresh frame.
+    pub fn draw(&self) -> PixelBuffer {
+        let mut buffer = PixelBuffer::new(self.resolution);
+        for (pos, cell) in self.grid.iter() {
+            if let Some(material) = cell.material {
+                buffer.set_pixel(
+                    (pos.x as u16, pos.y as u16).into(),
+                    self.materials.get(material).color(cell.shade),
+                );
+            }
+        }
+        buffer
+    }
+
+    // Writes the selection and brush, and the key bindings if asked for, over a frame.
+    fn draw_hud(&self, buffer: &mut PixelBuffer) {
+        let style = TextStyle::new(Pixel::white()).with_shadow(Pixel::black());
+        let name = &self.materials.get(self.selection).name;
+        let status = match self.brush.mode {
+            Mode::Fill => format!("{} r{}", name, self.brush.radius),
+            Mode::Spray => format!(
+                "{} r{} spray {}%",
+                name, self.brush.radius, self.brush.density
+            ),
+        };
+        buffer.draw_text(&status, (2, 2), style);
+        // Outline of the cells a click would paint.
+        let center = (self.mouse.0 as i32, self.mouse.1 as i32);
+        let radius = u16::try_from(self.brush.radius).unwrap_or_default();
+        match self.brush.shape {
+            Shape::Circle => buffer.draw_circle(center, radius, Pixel::white()),
+            Shape::Square => buffer.draw_rect(
+                (center.0 - i32::from(radius), center.1 - i32::from(radius)),
+                Resolution::new(2 * radius + 1, 2 * radius + 1),
+                Pixel::white(),
+            ),
+        }
+        if self.help {
+            buffer.draw_text(HELP, (2, 12), style);
+        } else {
+            buffer.draw_text("H help", (2, 12), style);
+        }
+    }
+
+    /// Replaces everything in the world with the automata of a level.
+    pub fn load_level(&mut self, level: &Level) {
+        self.ecs.clear();
+        self.grid = Grid::new(self.resolution);
+        for &(pos, material) in &level.automata {
+            self.spawn_automaton(pos, material);
+        }
+    }
+
+    /// Writes the whole state of the world to `path`, see `Snapshot` for the format.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let cells = self
+            .grid
+            .iter()
+            .map(|(_, cell)| SavedCell {
+                material: cell.material.map(|material| material.index() as u16),
+                shade: cell.shade,
+                temperature: cell.temperature,
+                velocity: cell
+                    .entity
+                    .and_then(|entity| self.ecs.get::<Velocity>(entity).ok())
+                    .map(|velocity| velocity.y),
+                lifetime: cell
+                    .entity
+                    .and_then(|entity| self.ecs.get::<Lifetime>(entity).ok())
+                    .map(|lifetime| lifetime.0),
+            })
+            .collect();
+        let snapshot = Snapshot {
+            width: self.resolution.width,
+            height: self.resolution.height,
+            rng: self.rng.get_seed(),
+            materials: (0..self.materials.len())
+                .map(|index| self.materials.get(Automata::from_index(index)).name.clone())
+                .collect(),
+            selection: self.selection.index() as u16,
+            cells,
+        };
+        let mut writer = BufWriter::new(File::create(path)?);
+        snapshot.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Replaces the state of the world with a snapshot written by `save`.
+    /// The world is left untouched if the file can't be used.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let snapshot = Snapshot::read(&mut BufReader::new(File::open(path)?), self.resolution)?;
+        let materials = snapshot
+            .materials
+            .iter()
+            .map(|name| {
+                self.materials
+                    .find(name)
+                    .ok_or_else(|| SnapshotError::UnknownMaterial(name.clone()))
+            })
+            .collect::<Result<Vec<Automata>, _>>()?;
+        let lookup = |index: u16| materials.get(usize::from(index)).copied();
+
+        let positions: Vec<Position> = self.grid.iter().map(|(pos, _)| pos).collect();
+        self.ecs.clear();
+        self.grid = Grid::new(self.resolution);
+        for (pos, saved) in positions.into_iter().zip(snapshot.cells) {
+            match saved.material.and_then(lookup) {
+                Some(material) if self.materials.get(material).rule == Rule::Static => {
+                    self.grid
+                        .fill(pos, material, None, saved.shade, saved.temperature);
+                }
+                Some(material) => {
+                    let mut builder = EntityBuilder::new();
+                    builder.add(material).add(pos);
+                    if let Some(y) = saved.velocity {
+                        builder.add(Velocity { y });
+                    }
+                    if let Some(ticks) = saved.lifetime {
+                        builder.add(Lifetime(ticks));
+                    }
+                    let entity = self.ecs.spawn(builder.build());
+                    self.grid
+                        .fill(pos, material, Some(entity), saved.shade, saved.temperature);
+                }
+                None => {
+                    if let Some(cell) = self.grid.get_mut(pos) {
+                        cell.temperature = saved.temperature;
+                    }
+                }
+            }
+        }
+        if let Some(selection) = lookup(snapshot.selection) {
+            self.selection = selection;
+        }
+        self.rng.seed(snapshot.rng);
+        Ok(())
+    }
+
+    /// Saves the current frame as a PNG named after the time it was taken.
+    pub fn screenshot(&self) {
+        let path = format!("screenshot-{}.png", timestamp());
+        match pixelbuffer::write_image(&self.draw(), &path) {
+            Ok(()) => println!("Saved {path}"),
+            Err(err) => println!("Couldn't save {path}: {err}"),
+        }
+    }
+
+    // Starts recording the presented frames, as a GIF or a directory of PNGs.
+    // Pressed again, it finishes the recording instead.
+    fn toggle_recording(&self, recording: &mut Option<Recorder>, gif: bool) {
+        if recording.is_some() {
+            finish_recording(recording);
+            return;
+        }
+        let name = format!("recording-{}", timestamp());
+        let recorder = if gif {
+            Recorder::gif(format!("{name}.gif"), self.resolution)
+        } else {
+            Recorder::png_sequence(&name, self.resolution)
+        };
+        match recorder {
+            Ok(recorder) => {
+                println!("Recording to {name}");
+                *recording = Some(recorder);
+            }
+            Err(err) => println!("Couldn't start recording {name}: {err}"),
+        }
+    }
+
+    /// Steps the simulation `ticks` times without a window and returns the final frame.
+    /// With a `replay`, every tick first handles the events recorded for it.
+    pub fn run_headless(&mut self, ticks: u64, mut replay: Option<InputLog>) -> PixelBuffer {
+        for frame in 0..ticks {
+            if let Some(ref mut log) = replay {
+                for event in replayed(log, frame) {
+                    if !self.handle_event(event) {
+                        return self.draw();
+                    }
+                }
+            }
+            self.step();
+        }
+        self.draw()
     }
 
     fn draw_sprites_system(
@@ -127,48 +557,507 @@ impl World {
         //self.draw_sprites_system(buffer, &mut q);
     }
 
-    pub fn start(&mut self) {
+    // Reacts to input that affects the simulation, returns false once it should stop.
+    fn handle_event(&mut self, event: Event) -> bool {
+        match event {
+            Event::Close => return false,
+            // Held brackets and - = keep adjusting the brush, other keys only act once per press.
+            Event::Key(glfw::Key::LeftBracket, Action::Press | Action::Repeat, _) => {
+                self.brush.resize(-1);
+            }
+            Event::Key(glfw::Key::RightBracket, Action::Press | Action::Repeat, _) => {
+                self.brush.resize(1);
+            }
+            Event::Key(glfw::Key::Minus, Action::Press | Action::Repeat, _) => {
+                self.brush.change_density(-5);
+            }
+            Event::Key(glfw::Key::Equal, Action::Press | Action::Repeat, _) => {
+                self.brush.change_density(5);
+            }
+            Event::Key(key, Action::Press, _) => match key {
+                glfw::Key::W => self.select("water"),
+                glfw::Key::S => self.select("sand"),
+                glfw::Key::A => self.cycle_selection(-1),
+                glfw::Key::D => self.cycle_selection(1),
+                glfw::Key::F => self.select("fire"),
+                glfw::Key::Space => self.select("walker"),
+                glfw::Key::F5 => match self.save(QUICKSAVE) {
+                    Ok(()) => println!("Saved to {QUICKSAVE}"),
+                    Err(err) => println!("Couldn't save {QUICKSAVE}: {err}"),
+                },
+                glfw::Key::F9 => match self.load(QUICKSAVE) {
+                    Ok(()) => println!("Loaded {QUICKSAVE}"),
+                    Err(err) => println!("Couldn't load {QUICKSAVE}: {err}"),
+                },
+                glfw::Key::F12 => self.screenshot(),
+                glfw::Key::Q => self.brush.toggle_shape(),
+                glfw::Key::E => self.brush.toggle_mode(),
+                glfw::Key::H => self.help = !self.help,
+                _ => println!("Pressed unhandled key {:?}", key),
+            },
+            Event::MouseButton(btn, action, _) => {
+                let tool = match btn {
+                    glfw::MouseButton::Button1 => Tool::Paint,
+                    glfw::MouseButton::Button2 => Tool::Erase,
+                    _ => {
+                        if action == Action::Press {
+                            println!("Pressed unhandled mouse button {btn:?}");
+                        }
+                        return true;
+                    }
+                };
+                match action {
+                    Action::Press => self.begin_stroke(tool),
+                    Action::Release => self.end_stroke(tool),
+                    Action::Repeat => (),
+                }
+            }
+            Event::Cursor((x, y)) => self.mouse = (x, y),
+            // Scrolling away from the user grows the brush.
+            Event::Scroll((_, y)) if y > 0.0 => self.brush.resize(1),
+            Event::Scroll((_, y)) if y < 0.0 => self.brush.resize(-1),
+            Event::Key(..) | Event::Scroll(_) | Event::CursorEnter(_) | Event::Resize(_) => (),
+        }
+        true
+    }
+
+    /// Opens a window and runs the simulation interactively until it is closed.
+    /// With a `replay`, input comes from the log instead and the window is only watched for
+    /// closing. Keys that save, load or record files are skipped while replaying.
+    /// With a `record`, every frame's input is written to it.
+    /// Every cell is shown as a `scale` by `scale` square of screen pixels.
+    pub fn start(
+        &mut self,
+        title: &str,
+        scale: u16,
+        replay: Option<InputLog>,
+        record: Option<InputRecorder>,
+    ) {
+        let mut window = Window::new(self.resolution, scale, title);
+        self.run(&mut window, replay, record);
+    }
+
+    /// Runs the simulation until `presenter` reports `Event::Close`, showing every tick on it.
+    /// `replay` and `record` work like they do for `start`.
+    pub fn run(
+        &mut self,
+        presenter: &mut impl Presenter,
+        mut replay: Option<InputLog>,
+        mut record: Option<InputRecorder>,
+    ) {
+        let mut recording = None;
+        let mut frame = 0;
         'running: loop {
-            let mut buffer: PixelBuffer = PixelBuffer::new(self.resolution);
-            let events = self.window.shown();
+            let mut events = presenter.poll_events();
+            if let Some(ref mut log) = replay {
+                events.retain(|event| matches!(event, Event::Close));
+                events.extend(replayed(log, frame));
+            }
+            if let Some(ref mut recorder) = record {
+                if let Err(err) = recorder.record(frame, &events) {
+                    println!("Stopped recording input: {err}");
+                    record = None;
+                }
+            }
             for event in events {
                 match event {
-                    Event::Close => break 'running,
-                    Event::Key(key) => match key {
-                        glfw::Key::W => self.selection = Automata::Water,
-                        glfw::Key::S => self.selection = Automata::Sand,
-                        glfw::Key::A => (),
-                        glfw::Key::D => (),
-                        glfw::Key::Space => self.selection = Automata::RandomWalker,
-                        _ => println!("Pressed unhandled key {:?}", key),
-                    },
-                    Event::MouseButton(btn) => match btn {
-                        glfw::MouseButton::Button1 => self
-                            .add_walkers(Position::new(self.mouse.0 as i64, self.mouse.1 as i64)),
-                        _ => println!("Pressed unhandled mouse button {:?}", btn),
-                    },
-                    Event::Cursor((x, y)) => self.mouse = (x, y),
+                    // Recording the screen doesn't change the simulation, so it's handled here.
+                    Event::Key(glfw::Key::R, Action::Press, _) => {
+                        self.toggle_recording(&mut recording, true);
+                    }
+                    Event::Key(glfw::Key::P, Action::Press, _) => {
+                        self.toggle_recording(&mut recording, false);
+                    }
+                    Event::Key(glfw::Key::F11, Action::Press, _) => presenter.toggle_fullscreen(),
+                    event => {
+                        if !self.handle_event(event) {
+                            break 'running;
+                        }
+                    }
                 }
             }
-            self.run_update_systems(&mut buffer);
+            self.step();
+            let mut buffer = self.draw();
             //self.run_pure_draw_systems(&mut buffer);
 
-            self.window.set_frame(buffer.get_buffer());
+            if let Some(ref mut recorder) = recording {
+                if let Err(err) = recorder.capture(buffer.pixels()) {
+                    println!("Stopped recording: {err}");
+                    recording = None;
+                }
+            }
+            // Recordings show only the simulation, the overlay is added afterwards.
+            self.draw_hud(&mut buffer);
+            presenter.present(buffer.get_buffer());
+            frame += 1;
+        }
+        finish_recording(&mut recording);
+    }
+}
+
+// Events recorded for `frame` without those that read or write files, so a replay leaves
+// quicksaves, screenshots and recordings alone.
+fn replayed(log: &mut InputLog, frame: u64) -> Vec<Event> {
+    let mut events = log.events(frame);
+    events.retain(|event| match *event {
+        Event::Key(glfw::Key::F9, Action::Press, _) => {
+            println!("Skipped loading {QUICKSAVE} on frame {frame}, the replay may diverge");
+            false
+        }
+        Event::Key(
+            glfw::Key::F5 | glfw::Key::F12 | glfw::Key::R | glfw::Key::P,
+            Action::Press,
+            _,
+        ) => false,
+        _ => true,
+    });
+    events
+}
+
+// Seconds and milliseconds since the epoch, to give saved files unique names.
+fn timestamp() -> String {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}-{:03}", time.as_secs(), time.subsec_millis())
+}
+
+fn finish_recording(recording: &mut Option<Recorder>) {
+    if let Some(recorder) = recording.take() {
+        match recorder.finish() {
+            Ok(frames) => println!("Recorded {frames} frames"),
+            Err(err) => println!("Couldn't finish the recording: {err}"),
         }
     }
 }
-/*
-fn system_remove_dead(world: &mut World) {
-    // Here we query entities with 0 or less hp and despawn them
-    let mut to_remove: Vec<Entity> = Vec::new();
-    for (id, hp) in &mut world.query::<&Health>() {
-        if hp.0 <= 0 {
-            to_remove.push(id);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glfw::{Modifiers, MouseButton};
+    use pixelbuffer::MemoryPresenter;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const RESOLUTION: Resolution = Resolution::new(32, 32);
+
+    fn world(seed: u64) -> World {
+        let materials = MaterialRegistry::parse(include_str!("../../materials.txt"))
+            .expect("the shipped materials are valid");
+        World::new(RESOLUTION, materials, seed)
+    }
+
+    // A file in the temp directory no other test uses, tests run in parallel in one process.
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let id = NEXT.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("glfwsand-{}-{id}-{name}", std::process::id()))
+    }
+
+    // Pours sand for a few frames, recorded the way `--record-input` does it.
+    fn pour_sand(seed: u64) -> InputLog {
+        let path = temp_path("pour.log");
+        let mut recorder = InputRecorder::create(&path, seed).expect("the log can be created");
+        let press = Event::MouseButton(MouseButton::Button1, Action::Press, Modifiers::empty());
+        let release = Event::MouseButton(MouseButton::Button1, Action::Release, Modifiers::empty());
+        recorder
+            .record(0, &[Event::Cursor((16.0, 4.0)), press])
+            .and_then(|()| recorder.record(10, &[Event::Cursor((8.0, 4.0))]))
+            .and_then(|()| recorder.record(20, &[release]))
+            .expect("the log can be written");
+        let log = InputLog::read(&path).expect("the log reads back");
+        std::fs::remove_file(&path).ok();
+        log
+    }
+
+    #[test]
+    fn replays_skip_file_keys() {
+        let path = temp_path("keys.log");
+        let press = |key| Event::Key(key, Action::Press, Modifiers::empty());
+        let mut recorder = InputRecorder::create(&path, 1).expect("the log can be created");
+        let keys = [
+            glfw::Key::F5,
+            glfw::Key::W,
+            glfw::Key::F9,
+            glfw::Key::F12,
+            glfw::Key::R,
+        ];
+        recorder
+            .record(0, &keys.map(press))
+            .expect("the log can be written");
+        drop(recorder);
+        let mut log = InputLog::read(&path).expect("the log reads back");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(replayed(&mut log, 0), vec![press(glfw::Key::W)]);
+    }
+
+    #[test]
+    fn same_seed_same_frames() {
+        let first = world(5).run_headless(80, Some(pour_sand(5)));
+        let second = world(5).run_headless(80, Some(pour_sand(5)));
+        assert!(first.pixels() == second.pixels());
+    }
+
+    #[test]
+    fn other_seed_other_frames() {
+        let first = world(5).run_headless(80, Some(pour_sand(5)));
+        let second = world(6).run_headless(80, Some(pour_sand(6)));
+        assert!(first.pixels() != second.pixels());
+    }
+
+    #[test]
+    fn headless_run() {
+        let mut world = world(1);
+        let sand = world.materials.find("sand").expect("sand is defined");
+        world.spawn_automaton(Position::new(10, 0), sand);
+        let frame = world.run_headless(100, None);
+        assert_eq!(<(u16, u16)>::from(frame.resolution()), (32, 32));
+        // The grain has fallen to the floor and nothing else is drawn.
+        let drawn: Vec<usize> = frame
+            .pixels()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &pixel)| pixel != Pixel::black())
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(drawn, vec![31 * 32 + 10]);
+    }
+
+    // Positions of every cell made of `material`, in row order.
+    fn cells_of(world: &World, material: Automata) -> Vec<Position> {
+        world
+            .grid
+            .iter()
+            .filter(|&(_, cell)| cell.material == Some(material))
+            .map(|(pos, _)| pos)
+            .collect()
+    }
+
+    #[test]
+    fn sand_sinks_below_water() {
+        let mut world = world(1);
+        let sand = world.materials.find("sand").expect("sand is defined");
+        let water = world.materials.find("water").expect("water is defined");
+        for y in 28..32 {
+            for x in 0..32 {
+                world.spawn_automaton(Position::new(x, y), water);
+            }
+        }
+        world.spawn_automaton(Position::new(16, 27), sand);
+        for _ in 0..200 {
+            world.step();
         }
+        let grains = cells_of(&world, sand);
+        assert_eq!(grains.len(), 1);
+        assert!(grains.iter().all(|grain| grain.y == 31), "{grains:?}");
+        assert_eq!(cells_of(&world, water).len(), 4 * 32);
     }
 
-    for entity in to_remove {
-        world.despawn(entity).unwrap();
+    #[test]
+    fn water_spreads_over_the_floor() {
+        let mut world = world(1);
+        let water = world.materials.find("water").expect("water is defined");
+        for y in 0..10 {
+            world.spawn_automaton(Position::new(16, y), water);
+        }
+        for _ in 0..300 {
+            world.step();
+        }
+        let drops = cells_of(&world, water);
+        assert_eq!(drops.len(), 10);
+        let mut columns: Vec<i64> = drops.iter().map(|drop| drop.x).collect();
+        columns.sort_unstable();
+        columns.dedup();
+        // Poured as one column, it has flowed out sideways into a shallow puddle.
+        assert!(columns.len() >= 5, "{drops:?}");
+        assert!(drops.iter().all(|drop| drop.y >= 29), "{drops:?}");
+    }
+
+    #[test]
+    fn gas_rises_and_dissipates() {
+        let mut world = world(1);
+        let smoke = world.materials.find("smoke").expect("smoke is defined");
+        let lifetime = world.materials.get(smoke).lifetime;
+        world.spawn_automaton(Position::new(16, 28), smoke);
+        for _ in 0..10 {
+            world.step();
+        }
+        let puffs = cells_of(&world, smoke);
+        assert_eq!(puffs.len(), 1);
+        assert!(puffs.iter().all(|puff| puff.y < 28), "{puffs:?}");
+        // Lifetimes are drawn from up to twice the material's.
+        for _ in 0..2 * lifetime {
+            world.step();
+        }
+        assert!(cells_of(&world, smoke).is_empty());
+        assert_eq!(world.ecs.query::<&Automata>().iter().count(), 0);
+    }
+
+    #[test]
+    fn hot_stone_melts() {
+        let mut world = world(1);
+        let stone = world.materials.find("stone").expect("stone is defined");
+        let lava = world.materials.find("lava").expect("lava is defined");
+        let pos = Position::new(16, 31);
+        world.spawn_automaton(pos, stone);
+        if let Some(cell) = world.grid.get_mut(pos) {
+            cell.temperature = 1500.0;
+        }
+        world.step();
+        assert_eq!(
+            world.grid.get(pos).and_then(|cell| cell.material),
+            Some(lava)
+        );
+    }
+
+    #[test]
+    fn fire_only_burns_what_it_saw() {
+        let mut world = world(1);
+        let fire = world.materials.find("fire").expect("fire is defined");
+        let oil = world.materials.find("oil").expect("oil is defined");
+        let water = world.materials.find("water").expect("water is defined");
+        let pos = Position::new(16, 31);
+        let beside = Position::new(17, 31);
+        let burner = world.spawn_automaton(pos, fire).expect("fire is an entity");
+        world.spawn_automaton(beside, water);
+        let burn = Intent::Transform {
+            offset: Position::new(1, 0),
+            from: oil,
+            into: fire,
+        };
+        world.apply_intents_system(vec![(burner, burn)]);
+        assert_eq!(
+            world.grid.get(beside).and_then(|cell| cell.material),
+            Some(water)
+        );
+    }
+
+    // Everything a snapshot stores about the grid, in row order.
+    fn cells(world: &World) -> Vec<(Option<Automata>, u8, u32)> {
+        world
+            .grid
+            .iter()
+            .map(|(_, cell)| (cell.material, cell.shade, cell.temperature.to_bits()))
+            .collect()
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut saved = world(3);
+        let smoke = saved.materials.find("smoke").expect("smoke is defined");
+        let stone = saved.materials.find("stone").expect("stone is defined");
+        saved.spawn_automaton(Position::new(3, 20), smoke);
+        saved.spawn_automaton(Position::new(4, 31), stone);
+        // Stops mid-pour, so there are grains in flight.
+        saved.run_headless(15, Some(pour_sand(3)));
+        let path = temp_path("world.sand");
+        saved.save(&path).expect("the world can be saved");
+        let mut loaded = world(4);
+        let result = loaded.load(&path);
+        std::fs::remove_file(&path).ok();
+        result.expect("the saved world loads");
+        assert_eq!(cells(&loaded), cells(&saved));
+        assert_eq!(loaded.rng.get_seed(), saved.rng.get_seed());
+        assert_eq!(loaded.selection, saved.selection);
+        let velocities = |world: &World| {
+            let mut velocities: Vec<u32> = world
+                .ecs
+                .query::<&Velocity>()
+                .iter()
+                .map(|(_, velocity)| velocity.y.to_bits())
+                .collect();
+            velocities.sort_unstable();
+            velocities
+        };
+        assert!(velocities(&saved).iter().any(|&y| y != 0.0_f32.to_bits()));
+        assert_eq!(velocities(&loaded), velocities(&saved));
+        assert_eq!(
+            loaded.ecs.query::<&Lifetime>().iter().count(),
+            saved.ecs.query::<&Lifetime>().iter().count()
+        );
+    }
+
+    #[test]
+    fn load_unknown_material() {
+        let mut world = world(1);
+        let lava = world.materials.find("lava").expect("lava is defined");
+        world.spawn_automaton(Position::new(16, 31), lava);
+        let path = temp_path("lava.sand");
+        world.save(&path).expect("the world can be saved");
+        let materials =
+            MaterialRegistry::parse("[sand]\ncolor = 1 2 3\nphase = powder\nrule = sand")
+                .expect("the materials are valid");
+        let mut sandbox = World::new(RESOLUTION, materials, 1);
+        let sand = sandbox.materials.find("sand").expect("sand is defined");
+        sandbox.spawn_automaton(Position::new(0, 0), sand);
+        let before = cells(&sandbox);
+        let result = sandbox.load(&path);
+        std::fs::remove_file(&path).ok();
+        // The world's own materials come first, so the first unknown one is reported.
+        assert!(
+            matches!(result, Err(SnapshotError::UnknownMaterial(ref name)) if name == "walker"),
+            "{result:?}"
+        );
+        assert_eq!(cells(&sandbox), before);
+    }
+
+    #[test]
+    fn ice_stays_put() {
+        let mut world = world(1);
+        let ice = world.materials.find("ice").expect("ice is defined");
+        let pos = Position::new(16, 4);
+        world.spawn_automaton(pos, ice);
+        for _ in 0..10 {
+            world.step();
+        }
+        assert_eq!(
+            world.grid.get(pos).and_then(|cell| cell.material),
+            Some(ice)
+        );
+    }
+
+    #[test]
+    fn lava_cools_into_stone() {
+        let mut world = world(1);
+        let lava = world.materials.find("lava").expect("lava is defined");
+        let stone = world.materials.find("stone").expect("stone is defined");
+        world.spawn_automaton(Position::new(16, 31), lava);
+        for _ in 0..2000 {
+            world.step();
+        }
+        let materials: Vec<Automata> = world
+            .grid
+            .iter()
+            .filter_map(|(_, cell)| cell.material)
+            .collect();
+        assert_eq!(materials, vec![stone]);
+    }
+
+    #[test]
+    fn run_with_scripted_events() {
+        let mut world = world(1);
+        let mut presenter = MemoryPresenter::new(RESOLUTION);
+        presenter.script(vec![
+            Event::Cursor((16.0, 8.0)),
+            Event::MouseButton(MouseButton::Button1, Action::Press, Modifiers::empty()),
+        ]);
+        presenter.script(Vec::new());
+        presenter.script(Vec::new());
+        presenter.script(vec![Event::MouseButton(
+            MouseButton::Button1,
+            Action::Release,
+            Modifiers::empty(),
+        )]);
+        world.run(&mut presenter, None, None);
+
+        // One frame per scripted poll, the run ends once the script does.
+        assert_eq!(presenter.frames().len(), 4);
+        let frame = presenter.last_frame().expect("frames were presented");
+        assert_eq!(<(u16, u16)>::from(frame.resolution()), (32, 32));
+        // The overlay is black and white, anything else is painted sand.
+        let painted = frame
+            .pixels()
+            .iter()
+            .any(|&pixel| pixel != Pixel::black() && pixel != Pixel::white());
+        assert!(painted);
     }
 }
-*/
@@ -0,0 +1,482 @@
+use crate::gfx::Pixel;
+use crate::ufb::Resolution;
+
+/// Size of a single character, glyphs are separated by a column and a row of empty pixels.
+pub const GLYPH_SIZE: Resolution = Resolution::new(5, 7);
+// Distance from one character to the next and from one line to the next.
+const ADVANCE: u16 = 6;
+const LINE_HEIGHT: u16 = 8;
+// Shown for characters the font doesn't have.
+const REPLACEMENT: u8 = b'?';
+
+// Rows of the printable ASCII characters from ` ` to `~`, top to bottom.
+// The leftmost pixel of a row is the highest of its five bits.
+const GLYPHS: [[u8; 7]; 95] = [
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000,
+    ], // space
+    [
+        0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100,
+    ], // !
+    [
+        0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000,
+    ], // "
+    [
+        0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010,
+    ], // #
+    [
+        0b00100, 0b01111, 0b10100, 0b01110, 0b00101, 0b11110, 0b00100,
+    ], // $
+    [
+        0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011,
+    ], // %
+    [
+        0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101,
+    ], // &
+    [
+        0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000,
+    ], // '
+    [
+        0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+    ], // (
+    [
+        0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+    ], // )
+    [
+        0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000,
+    ], // *
+    [
+        0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000,
+    ], // +
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000,
+    ], // ,
+    [
+        0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+    ], // -
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+    ], // .
+    [
+        0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000,
+    ], // /
+    [
+        0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+    ], // 0
+    [
+        0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ], // 1
+    [
+        0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+    ], // 2
+    [
+        0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+    ], // 3
+    [
+        0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+    ], // 4
+    [
+        0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+    ], // 5
+    [
+        0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+    ], // 6
+    [
+        0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+    ], // 7
+    [
+        0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+    ], // 8
+    [
+        0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+    ], // 9
+    [
+        0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000,
+    ], // :
+    [
+        0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000,
+    ], // ;
+    [
+        0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010,
+    ], // <
+    [
+        0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000,
+    ], // =
+    [
+        0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000,
+    ], // >
+    [
+        0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100,
+    ], // ?
+    [
+        0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110,
+    ], // @
+    [
+        0b01110, 0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001,
+    ], // A
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+    ], // B
+    [
+        0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+    ], // C
+    [
+        0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100,
+    ], // D
+    [
+        0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+    ], // E
+    [
+        0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+    ], // F
+    [
+        0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+    ], // G
+    [
+        0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+    ], // H
+    [
+        0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ], // I
+    [
+        0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+    ], // J
+    [
+        0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+    ], // K
+    [
+        0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+    ], // L
+    [
+        0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+    ], // M
+    [
+        0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+    ], // N
+    [
+        0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+    ], // O
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+    ], // P
+    [
+        0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+    ], // Q
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+    ], // R
+    [
+        0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+    ], // S
+    [
+        0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+    ], // T
+    [
+        0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+    ], // U
+    [
+        0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+    ], // V
+    [
+        0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+    ], // W
+    [
+        0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+    ], // X
+    [
+        0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100,
+    ], // Y
+    [
+        0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+    ], // Z
+    [
+        0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110,
+    ], // [
+    [
+        0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00001, 0b00000,
+    ], // backslash
+    [
+        0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110,
+    ], // ]
+    [
+        0b00100, 0b01010, 0b10001, 0b00000, 0b00000, 0b00000, 0b00000,
+    ], // ^
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+    ], // _
+    [
+        0b01000, 0b00100, 0b00010, 0b00000, 0b00000, 0b00000, 0b00000,
+    ], // `
+    [
+        0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111,
+    ], // a
+    [
+        0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110,
+    ], // b
+    [
+        0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110,
+    ], // c
+    [
+        0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111,
+    ], // d
+    [
+        0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110,
+    ], // e
+    [
+        0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000,
+    ], // f
+    [
+        0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110,
+    ], // g
+    [
+        0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001,
+    ], // h
+    [
+        0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ], // i
+    [
+        0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b10010, 0b01100,
+    ], // j
+    [
+        0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010,
+    ], // k
+    [
+        0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ], // l
+    [
+        0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001,
+    ], // m
+    [
+        0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001,
+    ], // n
+    [
+        0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110,
+    ], // o
+    [
+        0b00000, 0b00000, 0b11110, 0b10001, 0b11110, 0b10000, 0b10000,
+    ], // p
+    [
+        0b00000, 0b00000, 0b01101, 0b10011, 0b01111, 0b00001, 0b00001,
+    ], // q
+    [
+        0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000,
+    ], // r
+    [
+        0b00000, 0b00000, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110,
+    ], // s
+    [
+        0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110,
+    ], // t
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101,
+    ], // u
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+    ], // v
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010,
+    ], // w
+    [
+        0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001,
+    ], // x
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110,
+    ], // y
+    [
+        0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111,
+    ], // z
+    [
+        0b00010, 0b00100, 0b00100, 0b01000, 0b00100, 0b00100, 0b00010,
+    ], // {
+    [
+        0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+    ], // |
+    [
+        0b01000, 0b00100, 0b00100, 0b00010, 0b00100, 0b00100, 0b01000,
+    ], // }
+    [
+        0b00000, 0b00000, 0b01000, 0b10101, 0b00010, 0b00000, 0b00000,
+    ], // ~
+];
+
+/// Colors `PixelBuffer::draw_text` draws with.
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+    pub color: Pixel,
+    // Drawn one pixel below and to the right of the text, to keep it readable on any background.
+    pub shadow: Option<Pixel>,
+}
+
+impl TextStyle {
+    #[must_use]
+    pub const fn new(color: Pixel) -> Self {
+        Self {
+            color,
+            shadow: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_shadow(self, shadow: Pixel) -> Self {
+        Self {
+            shadow: Some(shadow),
+            ..self
+        }
+    }
+}
+
+/// Pixels covered by `text` drawn with `PixelBuffer::draw_text`, leaving out the shadow.
+/// Every `\n` starts a new line.
+#[must_use]
+pub fn text_size(text: &str) -> Resolution {
+    let columns = text.lines().map(|line| line.chars().count()).max();
+    let lines = text.lines().count();
+    // The gap after the last character or line isn't part of the text.
+    let extent = |count: usize, step: u16, glyph: u16| {
+        u16::try_from(count)
+            .unwrap_or(u16::MAX)
+            .checked_sub(1)
+            .map_or(0, |gaps| gaps.saturating_mul(step).saturating_add(glyph))
+    };
+    Resolution::new(
+        extent(columns.unwrap_or_default(), ADVANCE, GLYPH_SIZE.width),
+        extent(lines, LINE_HEIGHT, GLYPH_SIZE.height),
+    )
+}
+
+// Offsets from the top left corner of the text of every pixel its glyphs light up.
+pub(crate) fn text_pixels(text: &str) -> impl Iterator<Item = (i32, i32)> + '_ {
+    let width = i32::from(GLYPH_SIZE.width);
+    (0..).zip(text.lines()).flat_map(move |(line, characters)| {
+        (0..)
+            .zip(characters.chars())
+            .flat_map(move |(column, character)| {
+                let left = column * i32::from(ADVANCE);
+                let top = line * i32::from(LINE_HEIGHT);
+                (0..).zip(glyph(character)).flat_map(move |(y, row)| {
+                    (0..width)
+                        .filter(move |x| row & (1 << (width - 1 - x)) != 0)
+                        .map(move |x| (left + x, top + y))
+                })
+            })
+    })
+}
+
+fn glyph(character: char) -> [u8; 7] {
+    let byte = u8::try_from(character)
+        .ok()
+        .filter(|byte| (b' '..=b'~').contains(byte))
+        .unwrap_or(REPLACEMENT);
+    GLYPHS
+        .get(usize::from(byte - b' '))
+        .copied()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::PixelBuffer;
+
+    const RED: Pixel = Pixel::new(255, 0, 0);
+
+    // Pixels of the given color after drawing `text` at `top_left` onto an empty buffer.
+    fn drawn(text: &str, top_left: (i32, i32), style: TextStyle, color: Pixel) -> Vec<(i32, i32)> {
+        let mut buffer = PixelBuffer::new(Resolution::new(16, 18));
+        buffer.draw_text(text, top_left, style);
+        (0..18)
+            .flat_map(|y| (0..16).map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                let coords = (
+                    u16::try_from(x).unwrap_or_default(),
+                    u16::try_from(y).unwrap_or_default(),
+                );
+                buffer.get_pixel(coords.into()) == Some(color)
+            })
+            .collect()
+    }
+
+    fn white(text: &str, top_left: (i32, i32)) -> Vec<(i32, i32)> {
+        drawn(
+            text,
+            top_left,
+            TextStyle::new(Pixel::white()),
+            Pixel::white(),
+        )
+    }
+
+    #[test]
+    fn glyph_pixels() {
+        let mut expected: Vec<(i32, i32)> = vec![(7, 0), (8, 0), (9, 0)];
+        // The dash sits on the middle row, the `I` starts one advance to the right.
+        expected.extend([(0, 3), (1, 3), (2, 3), (3, 3), (4, 3)]);
+        expected.extend((1..6).map(|y| (8, y)));
+        expected.extend([(7, 6), (8, 6), (9, 6)]);
+        expected.sort_unstable_by_key(|&(x, y)| (y, x));
+        assert_eq!(white("-I", (0, 0)), expected);
+    }
+
+    #[test]
+    fn unknown_characters_are_replaced() {
+        assert_eq!(white("é", (2, 2)), white("?", (2, 2)));
+        assert!(white(" ", (2, 2)).is_empty());
+    }
+
+    #[test]
+    fn shadow_is_offset_by_one_pixel() {
+        let style = TextStyle::new(Pixel::white()).with_shadow(RED);
+        assert_eq!(
+            drawn("-", (0, 0), style, Pixel::white()),
+            white("-", (0, 0))
+        );
+        // The text covers the shadow where they overlap.
+        assert_eq!(drawn("-", (0, 0), style, RED), white("-", (1, 1)));
+    }
+
+    #[test]
+    fn lines() {
+        let mut expected = white("-", (0, 0));
+        expected.extend(white("-", (0, 8)));
+        assert_eq!(white("-\n-", (0, 0)), expected);
+        assert_eq!(white("\n-", (0, 0)), white("-", (0, 8)));
+    }
+
+    #[test]
+    fn clipped_text() {
+        // Shifted up and left, only the bottom of the `I` remains.
+        assert_eq!(white("I", (-2, -5)), vec![(0, 0), (0, 1), (1, 1)]);
+        // Only the left edge of the `H` fits on the right.
+        assert_eq!(white("H", (15, 0)), white("|", (13, 0)));
+    }
+
+    #[test]
+    fn far_away_text() {
+        for top_left in [
+            (i32::MAX, 0),
+            (0, i32::MAX),
+            (i32::MAX - 3, i32::MAX - 3),
+            (i32::MIN, i32::MIN),
+            (-1_000, 4),
+        ] {
+            let style = TextStyle::new(Pixel::white()).with_shadow(RED);
+            assert!(drawn("Hi\nthere", top_left, style, Pixel::white()).is_empty());
+            assert!(drawn("Hi\nthere", top_left, style, RED).is_empty());
+        }
+    }
+
+    #[test]
+    fn size_of_text() {
+        assert_eq!(<(u16, u16)>::from(text_size("")), (0, 0));
+        assert_eq!(<(u16, u16)>::from(text_size("I")), (5, 7));
+        assert_eq!(<(u16, u16)>::from(text_size("ab\nc")), (11, 15));
+        // Every pixel of the text lies within its size.
+        let text = "H help\nA/D cycle";
+        let (width, height) = text_size(text).into();
+        let pixels: Vec<(i32, i32)> = text_pixels(text).collect();
+        assert!(pixels
+            .iter()
+            .all(|&(x, y)| x < i32::from(width) && y < i32::from(height)));
+        assert!(pixels.iter().any(|&(x, _)| x == i32::from(width) - 1));
+        assert!(pixels.iter().any(|&(_, y)| y == i32::from(height) - 1));
+    }
+}
@@ -1,7 +1,10 @@
+use crate::font::{text_pixels, TextStyle};
+use crate::image::{encode_png, encode_ppm, ImageError};
 use crate::ufb::Resolution;
 use std::default::Default;
+use std::io::Write;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Pixel {
     r: u8,
     g: u8,
@@ -30,6 +33,19 @@ impl Pixel {
     }
 }
 
+impl From<[u8; 3]> for Pixel {
+    fn from(rgb: [u8; 3]) -> Self {
+        let [r, g, b] = rgb;
+        Self::new(r, g, b)
+    }
+}
+
+impl From<Pixel> for [u8; 3] {
+    fn from(pixel: Pixel) -> Self {
+        [pixel.r, pixel.g, pixel.b]
+    }
+}
+
 impl Default for Pixel {
     fn default() -> Self {
         Self::alpha()
@@ -50,6 +66,31 @@ impl PixelBuffer {
         }
     }
 
+    /// Wraps existing pixels in row order, `None` if their count doesn't match the resolution.
+    #[must_use]
+    pub fn from_pixels(resolution: Resolution, pixels: Vec<Pixel>) -> Option<Self> {
+        (pixels.len() == resolution.area()).then_some(Self {
+            bounds: resolution,
+            buffer: pixels,
+        })
+    }
+
+    #[must_use]
+    pub const fn resolution(&self) -> Resolution {
+        self.bounds
+    }
+
+    #[must_use]
+    pub fn get_pixel(&self, coords: FramebufferCoordinates) -> Option<Pixel> {
+        let (x, y) = coords.into();
+        if x >= self.bounds.width || y >= self.bounds.height {
+            return None;
+        }
+        self.buffer
+            .get(usize::from(x) + usize::from(y) * usize::from(self.bounds.width))
+            .copied()
+    }
+
     #[must_use]
     pub fn free(&self, coords: FramebufferCoordinates) -> bool {
         let (x, y) = coords.into();
@@ -111,10 +152,353 @@ impl PixelBuffer {
         }
     }
 
+    /// Writes `text` with its top left corner at `top_left` in the built-in 5x7 font.
+    /// Every `\n` starts a new line and whatever falls outside the buffer is cut off.
+    pub fn draw_text(&mut self, text: &str, top_left: (i32, i32), style: TextStyle) {
+        let (left, top) = top_left;
+        if let Some(shadow) = style.shadow {
+            for (x, y) in text_pixels(text) {
+                self.plot(
+                    left.saturating_add(x + 1),
+                    top.saturating_add(y + 1),
+                    shadow,
+                );
+            }
+        }
+        for (x, y) in text_pixels(text) {
+            self.plot(left.saturating_add(x), top.saturating_add(y), style.color);
+        }
+    }
+
+    /// Draws a one pixel wide line from `from` to `to`, both ends included.
+    /// Only the part inside the buffer is walked, however far away the ends are.
+    pub fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), pixel: Pixel) {
+        let (x0, y0) = (i128::from(from.0), i128::from(from.1));
+        let (dx, dy) = (i128::from(to.0) - x0, i128::from(to.1) - y0);
+        // The line takes one step along its longer axis per pixel.
+        let steps = dx.abs().max(dy.abs());
+        if steps == 0 {
+            self.plot(from.0, from.1, pixel);
+            return;
+        }
+        // Pixel at step `i`, the shorter axis rounded to the nearest pixel as Bresenham's does.
+        let offset = |delta: i128, i: i128| {
+            delta.signum() * (2 * delta.abs() * i + steps).div_euclid(2 * steps)
+        };
+        let point = |i: i128| (x0 + offset(dx, i), y0 + offset(dy, i));
+
+        // Both coordinates move in one direction only, so the steps inside the buffer are a
+        // single run whose ends can be found by bisection.
+        let (mut first, mut last) = (0, steps);
+        let limits = [
+            (dx, i128::from(self.bounds.width)),
+            (dy, i128::from(self.bounds.height)),
+        ];
+        for (axis, (delta, limit)) in limits.into_iter().enumerate() {
+            let coordinate = |i: i128| if axis == 0 { point(i).0 } else { point(i).1 };
+            let entered = |i: i128| {
+                let c = coordinate(i);
+                if delta >= 0 {
+                    c >= 0
+                } else {
+                    c < limit
+                }
+            };
+            let left = |i: i128| {
+                let c = coordinate(i);
+                if delta >= 0 {
+                    c >= limit
+                } else {
+                    c < 0
+                }
+            };
+            first = first.max(partition_point(0, steps, entered));
+            last = last.min(partition_point(0, steps, left) - 1);
+        }
+        if first > last {
+            return;
+        }
+
+        // Bresenham over the visible steps, starting with the error term step `first` has.
+        let (x_major, minor_delta) = if dx.abs() >= dy.abs() {
+            (true, dy)
+        } else {
+            (false, dx)
+        };
+        let numerator = 2 * minor_delta.abs() * first + steps;
+        let mut minor = numerator.div_euclid(2 * steps);
+        let mut error = numerator.rem_euclid(2 * steps);
+        for i in first..=last {
+            let (major_offset, minor_offset) = (i, minor_delta.signum() * minor);
+            let (x, y) = if x_major {
+                (x0 + dx.signum() * major_offset, y0 + minor_offset)
+            } else {
+                (x0 + minor_offset, y0 + dy.signum() * major_offset)
+            };
+            if let (Ok(x), Ok(y)) = (i32::try_from(x), i32::try_from(y)) {
+                self.plot(x, y, pixel);
+            }
+            error += 2 * minor_delta.abs();
+            if error >= 2 * steps {
+                error -= 2 * steps;
+                minor += 1;
+            }
+        }
+    }
+
+    /// Draws the border of a rectangle, one pixel wide and inside `size`.
+    pub fn draw_rect(&mut self, top_left: (i32, i32), size: Resolution, pixel: Pixel) {
+        let (left, top) = top_left;
+        let (width, height) = (i32::from(size.width), i32::from(size.height));
+        if width == 0 || height == 0 {
+            return;
+        }
+        let (right, bottom) = (left + width - 1, top + height - 1);
+        self.draw_line((left, top), (right, top), pixel);
+        self.draw_line((left, bottom), (right, bottom), pixel);
+        self.draw_line((left, top), (left, bottom), pixel);
+        self.draw_line((right, top), (right, bottom), pixel);
+    }
+
+    /// Sets every pixel of the rectangle with its top left corner at `top_left`.
+    pub fn fill_rect(&mut self, top_left: (i32, i32), size: Resolution, pixel: Pixel) {
+        let (left, top) = top_left;
+        for y in top..top + i32::from(size.height) {
+            self.span(left, left + i32::from(size.width) - 1, y, pixel);
+        }
+    }
+
+    /// Draws the border of a circle, an ellipse with both radii `radius`.
+    pub fn draw_circle(&mut self, center: (i32, i32), radius: u16, pixel: Pixel) {
+        self.draw_ellipse(center, (radius, radius), pixel);
+    }
+
+    /// Sets every pixel within `radius` of `center`, a radius of 0 being the center alone.
+    pub fn fill_circle(&mut self, center: (i32, i32), radius: u16, pixel: Pixel) {
+        self.fill_ellipse(center, (radius, radius), pixel);
+    }
+
+    /// Draws the border of an axis aligned ellipse, `radii` being its horizontal and vertical
+    /// distance from the center to the edge. The border is the outermost pixels
+    /// `fill_ellipse` sets.
+    pub fn draw_ellipse(&mut self, center: (i32, i32), radii: (u16, u16), pixel: Pixel) {
+        let (center_x, center_y) = center;
+        let widths = ellipse_widths(radii);
+        let width = |row: Option<usize>| {
+            row.and_then(|row| widths.get(row))
+                .map_or(-1, |&width| width)
+        };
+        for (row, dy) in (-i32::from(radii.1)..=i32::from(radii.1)).enumerate() {
+            let outer = width(Some(row));
+            // Pixels are on the border where the rows above or below are narrower.
+            let inner = width(row.checked_sub(1))
+                .min(width(Some(row + 1)))
+                .min(outer - 1);
+            let y = center_y + dy;
+            self.span(center_x - outer, center_x - inner - 1, y, pixel);
+            self.span(center_x + inner + 1, center_x + outer, y, pixel);
+        }
+    }
+
+    /// Sets every pixel whose center lies in the ellipse, which reaches `radii` pixels
+    /// beyond the center pixel horizontally and vertically.
+    pub fn fill_ellipse(&mut self, center: (i32, i32), radii: (u16, u16), pixel: Pixel) {
+        let (center_x, center_y) = center;
+        let rows = -i32::from(radii.1)..=i32::from(radii.1);
+        for (dy, width) in rows.zip(ellipse_widths(radii)) {
+            self.span(center_x - width, center_x + width, center_y + dy, pixel);
+        }
+    }
+
+    /// Draws the outline of the polygon through `points`, closing it from the last to the first.
+    pub fn draw_polygon(&mut self, points: &[(i32, i32)], pixel: Pixel) {
+        for (index, &from) in points.iter().enumerate() {
+            let to = points.get(index + 1).or_else(|| points.first());
+            if let Some(&to) = to {
+                self.draw_line(from, to, pixel);
+            }
+        }
+    }
+
+    /// Fills the polygon through `points` with the even-odd rule, so overlapping parts of a
+    /// self-intersecting polygon are left out. A pixel is inside if its center is.
+    pub fn fill_polygon(&mut self, points: &[(i32, i32)], pixel: Pixel) {
+        let edges: Vec<((i32, i32), (i32, i32))> = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(&from, &to)| (from, to))
+            .filter(|&(from, to)| from.1 != to.1)
+            .collect();
+        let rows = points.iter().map(|&(_, y)| y);
+        let top = rows.clone().min().unwrap_or_default().max(0);
+        let bottom = rows
+            .max()
+            .unwrap_or_default()
+            .min(i32::from(self.bounds.height) - 1);
+        let mut crossings = Vec::new();
+        for y in top..=bottom {
+            crossings.clear();
+            for &((x0, y0), (x1, y1)) in &edges {
+                if y0.min(y1) <= y && y < y0.max(y1) {
+                    crossings.push(first_pixel_right_of((x0, y0), (x1, y1), y));
+                }
+            }
+            crossings.sort_unstable();
+            for pair in crossings.chunks_exact(2) {
+                if let [left, right] = *pair {
+                    self.span(left, right - 1, y, pixel);
+                }
+            }
+        }
+    }
+
+    /// Replaces the color of the area around `start` that has the same color as it,
+    /// spreading to the four direct neighbours of every pixel.
+    pub fn flood_fill(&mut self, start: (i32, i32), pixel: Pixel) {
+        let Some(target) = self.pixel_at(start.0, start.1) else {
+            return;
+        };
+        // `set_pixel` skips the transparent color, so filling with it would never finish.
+        if target == pixel || pixel == Pixel::alpha() {
+            return;
+        }
+        let mut seeds = vec![start];
+        while let Some((x, y)) = seeds.pop() {
+            if self.pixel_at(x, y) != Some(target) {
+                continue;
+            }
+            // Fills the whole run of the row the seed is in, then looks for runs above and below.
+            let mut left = x;
+            while self.pixel_at(left - 1, y) == Some(target) {
+                left -= 1;
+            }
+            let mut right = x;
+            while self.pixel_at(right + 1, y) == Some(target) {
+                right += 1;
+            }
+            self.span(left, right, y, pixel);
+            for row in [y - 1, y + 1] {
+                let mut inside = false;
+                for column in left..=right {
+                    let matches = self.pixel_at(column, row) == Some(target);
+                    if matches && !inside {
+                        seeds.push((column, row));
+                    }
+                    inside = matches;
+                }
+            }
+        }
+    }
+
+    // Color of a pixel that may lie outside the buffer, `None` if it does.
+    fn pixel_at(&self, x: i32, y: i32) -> Option<Pixel> {
+        let (x, y) = (u16::try_from(x).ok()?, u16::try_from(y).ok()?);
+        self.get_pixel((x, y).into())
+    }
+
+    // Sets the pixels of row `y` from `left` to `right`, both included, that are in the buffer.
+    fn span(&mut self, left: i32, right: i32, y: i32, pixel: Pixel) {
+        if y < 0 || y >= i32::from(self.bounds.height) {
+            return;
+        }
+        for x in left.max(0)..=right.min(i32::from(self.bounds.width) - 1) {
+            self.plot(x, y, pixel);
+        }
+    }
+
+    // Sets a pixel that may lie outside the buffer, in which case nothing happens.
+    fn plot(&mut self, x: i32, y: i32, pixel: Pixel) {
+        if let (Ok(x), Ok(y)) = (u16::try_from(x), u16::try_from(y)) {
+            if x < self.bounds.width && y < self.bounds.height {
+                self.set_pixel((x, y).into(), pixel);
+            }
+        }
+    }
+
     #[must_use]
     pub fn get_buffer(self) -> Vec<Pixel> {
         self.buffer
     }
+
+    #[must_use]
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.buffer
+    }
+
+    /// Encodes the buffer as an 8-bit RGB PNG.
+    ///
+    /// # Errors
+    /// Fails if the writer does.
+    pub fn write_png(&self, writer: impl Write) -> Result<(), ImageError> {
+        encode_png(self, writer)
+    }
+
+    /// Encodes the buffer as a binary (`P6`) PPM.
+    ///
+    /// # Errors
+    /// Fails if the writer does.
+    pub fn write_ppm(&self, writer: impl Write) -> std::io::Result<()> {
+        encode_ppm(self, writer)
+    }
+}
+
+// First of the values from `low` to `high` for which `test` holds, or `high + 1` if there is
+// none. The test has to fail for a run of values and hold for all after it.
+fn partition_point(low: i128, high: i128, test: impl Fn(i128) -> bool) -> i128 {
+    let (mut low, mut high) = (low, high + 1);
+    while low < high {
+        let middle = low + (high - low).div_euclid(2);
+        if test(middle) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    low
+}
+
+// Half widths of the rows of a filled ellipse from top to bottom, the rim running through
+// the outer edges of the pixels at the tips. Works on doubled coordinates to stay in integers,
+// wide enough that even the largest radii can't overflow.
+fn ellipse_widths(radii: (u16, u16)) -> Vec<i32> {
+    let (radius_x, radius_y) = (i128::from(radii.0), i128::from(radii.1));
+    let (outer_x, outer_y) = ((2 * radius_x + 1).pow(2), (2 * radius_y + 1).pow(2));
+    // Rows narrow away from the middle, so the width only ever shrinks while walking outwards.
+    let mut width = radius_x;
+    let lower: Vec<i32> = (0..=radius_y)
+        .map(|dy| {
+            let row = (2 * dy).pow(2) * outer_x;
+            while width > 0 && (2 * width).pow(2) * outer_y + row > outer_x * outer_y {
+                width -= 1;
+            }
+            i32::try_from(width).unwrap_or_default()
+        })
+        .collect();
+    lower
+        .iter()
+        .rev()
+        .chain(lower.iter().skip(1))
+        .copied()
+        .collect()
+}
+
+// First pixel whose center lies right of where the edge from `from` to `to` crosses the
+// center of row `y`, so polygons sharing an edge don't both fill the pixels on it.
+fn first_pixel_right_of(from: (i32, i32), to: (i32, i32), y: i32) -> i32 {
+    let ((x0, y0), (x1, y1)) = (
+        (i64::from(from.0), i64::from(from.1)),
+        (i64::from(to.0), i64::from(to.1)),
+    );
+    let rise = y1 - y0;
+    // The crossing less half a pixel as a fraction, rounded up.
+    let mut numerator = 2 * x0 * rise + (2 * i64::from(y) + 1 - 2 * y0) * (x1 - x0) - rise;
+    let mut denominator = 2 * rise;
+    if denominator < 0 {
+        numerator = -numerator;
+        denominator = -denominator;
+    }
+    let ceiling = -(-numerator).div_euclid(denominator);
+    i32::try_from(ceiling).unwrap_or(if ceiling < 0 { i32::MIN } else { i32::MAX })
 }
 
 #[derive(Clone, Copy)]
@@ -144,3 +528,256 @@ pub fn xy(index: usize, resolution: Resolution) -> FramebufferCoordinates {
         FramebufferCoordinates::from((x, y))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer() -> PixelBuffer {
+        PixelBuffer::new(Resolution::new(16, 12))
+    }
+
+    fn lit(buffer: &PixelBuffer) -> Vec<(u16, u16)> {
+        buffer
+            .pixels()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &pixel)| pixel != Pixel::black())
+            .map(|(index, _)| xy(index, buffer.resolution()).into())
+            .collect()
+    }
+
+    // Every pixel of the line, whether it's in the buffer or not.
+    fn unclipped_line(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+        let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs());
+        let offset = |delta: i32, i: i32| {
+            delta.signum() * (2 * delta.abs() * i + steps).div_euclid(2 * steps.max(1))
+        };
+        (0..=steps)
+            .map(|i| {
+                (
+                    from.0 + offset(to.0 - from.0, i),
+                    from.1 + offset(to.1 - from.1, i),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn line_includes_both_ends() {
+        for (from, to) in [((1, 1), (14, 5)), ((3, 10), (4, 0)), ((7, 7), (7, 7))] {
+            let mut buffer = buffer();
+            buffer.draw_line(from, to, Pixel::white());
+            let lit = lit(&buffer);
+            for (x, y) in [from, to] {
+                let end = (
+                    u16::try_from(x).unwrap_or_default(),
+                    u16::try_from(y).unwrap_or_default(),
+                );
+                assert!(lit.contains(&end), "{end:?} of {from:?} to {to:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn clipped_line_matches_unclipped() {
+        let ends: Vec<(i32, i32)> = (-20..36)
+            .step_by(7)
+            .flat_map(|x| (-15..30).step_by(6).map(move |y| (x, y)))
+            .collect();
+        for &from in &ends {
+            for &to in &ends {
+                let mut buffer = buffer();
+                buffer.draw_line(from, to, Pixel::white());
+                let mut expected: Vec<(u16, u16)> = unclipped_line(from, to)
+                    .into_iter()
+                    .filter_map(|(x, y)| Some((u16::try_from(x).ok()?, u16::try_from(y).ok()?)))
+                    .filter(|&(x, y)| x < 16 && y < 12)
+                    .collect();
+                expected.sort_unstable_by_key(|&(x, y)| (y, x));
+                expected.dedup();
+                assert_eq!(lit(&buffer), expected, "{from:?} to {to:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn far_away_line_ends() {
+        let mut buffer = buffer();
+        buffer.draw_line((i32::MIN, i32::MIN), (i32::MAX, i32::MAX), Pixel::white());
+        buffer.draw_line((i32::MIN, 5), (i32::MAX, 5), Pixel::white());
+        buffer.draw_line((i32::MAX, i32::MIN), (i32::MIN, i32::MAX), Pixel::white());
+        assert!(lit(&buffer).contains(&(0, 5)));
+        assert!(lit(&buffer).contains(&(15, 5)));
+    }
+
+    #[test]
+    fn outlines_are_the_rims_of_fills() {
+        let radii = [(0, 0), (1, 1), (3, 2), (5, 5), (2, 5), (7, 1)];
+        for radii in radii {
+            let (mut filled, mut outline) = (buffer(), buffer());
+            filled.fill_ellipse((8, 6), radii, Pixel::white());
+            outline.draw_ellipse((8, 6), radii, Pixel::white());
+            let inside = lit(&filled);
+            // The rim are the filled pixels with a direct neighbour outside the fill.
+            let rim: Vec<(u16, u16)> = inside
+                .iter()
+                .copied()
+                .filter(|&(x, y)| {
+                    [
+                        (x + 1, y),
+                        (x.wrapping_sub(1), y),
+                        (x, y + 1),
+                        (x, y.wrapping_sub(1)),
+                    ]
+                    .iter()
+                    .any(|neighbour| !inside.contains(neighbour))
+                })
+                .collect();
+            assert_eq!(lit(&outline), rim, "radii {radii:?}");
+        }
+        let (mut filled, mut outline) = (buffer(), buffer());
+        filled.fill_rect((2, 3), Resolution::new(6, 4), Pixel::white());
+        outline.draw_rect((2, 3), Resolution::new(6, 4), Pixel::white());
+        assert_eq!(lit(&filled).len(), 24);
+        assert_eq!(lit(&outline).len(), 16);
+        assert!(lit(&outline)
+            .iter()
+            .all(|pixel| lit(&filled).contains(pixel)));
+    }
+
+    #[test]
+    fn flood_fill_stops_at_borders() {
+        let mut buffer = buffer();
+        let red = Pixel::new(255, 0, 0);
+        buffer.draw_rect((2, 2), Resolution::new(6, 6), Pixel::white());
+        buffer.flood_fill((4, 4), red);
+        let filled = buffer
+            .pixels()
+            .iter()
+            .filter(|&&pixel| pixel == red)
+            .count();
+        assert_eq!(filled, 16);
+        assert_eq!(buffer.get_pixel((0, 0).into()), Some(Pixel::black()));
+
+        // Filling with the color already there changes nothing and finishes.
+        let before = buffer.pixels().to_vec();
+        buffer.flood_fill((4, 4), red);
+        buffer.flood_fill((-1, 4), Pixel::white());
+        assert!(buffer.pixels() == before.as_slice());
+    }
+
+    // Pixels lit by drawing in white onto an empty buffer.
+    fn drawn(draw: impl FnOnce(&mut PixelBuffer, Pixel)) -> Vec<(u16, u16)> {
+        let mut buffer = buffer();
+        draw(&mut buffer, Pixel::white());
+        lit(&buffer)
+    }
+
+    // Every pixel from `from` to `to`, both included, in row order.
+    fn block(from: (u16, u16), to: (u16, u16)) -> Vec<(u16, u16)> {
+        (from.1..=to.1)
+            .flat_map(|y| (from.0..=to.0).map(move |x| (x, y)))
+            .collect()
+    }
+
+    #[test]
+    fn partly_visible_shapes() {
+        let rect = drawn(|buffer, white| buffer.fill_rect((-5, -5), Resolution::new(8, 8), white));
+        assert_eq!(rect, block((0, 0), (2, 2)));
+
+        // Only the top and left sides of this outline are on screen.
+        let outline =
+            drawn(|buffer, white| buffer.draw_rect((10, 8), Resolution::new(20, 20), white));
+        let mut expected = block((10, 8), (15, 8));
+        expected.extend([(10, 9), (10, 10), (10, 11)]);
+        assert_eq!(outline, expected);
+
+        let circle = drawn(|buffer, white| buffer.draw_circle((0, 11), 6, white));
+        assert!(circle.contains(&(0, 5)) && circle.contains(&(6, 11)));
+        assert!(!circle.contains(&(0, 11)) && !circle.contains(&(3, 11)));
+        assert!(circle.iter().all(|&(x, y)| x <= 6 && y >= 5));
+
+        let disc = drawn(|buffer, white| buffer.fill_circle((15, 0), 4, white));
+        assert!(disc.contains(&(11, 0)) && disc.contains(&(15, 4)) && disc.contains(&(13, 2)));
+        assert!(!disc.contains(&(10, 0)) && !disc.contains(&(15, 5)) && !disc.contains(&(11, 4)));
+
+        // Centered left of the buffer, so only its right half shows.
+        let ellipse = drawn(|buffer, white| buffer.fill_ellipse((-3, 6), (5, 9), white));
+        assert!(
+            ellipse.contains(&(2, 6)) && ellipse.contains(&(0, 0)) && ellipse.contains(&(0, 11))
+        );
+        assert!(!ellipse.contains(&(3, 6)) && !ellipse.contains(&(2, 0)));
+        assert!(ellipse.iter().all(|&(x, _)| x <= 2));
+
+        // A triangle reaching past the left, right and bottom edges.
+        let triangle = [(-4, 2), (20, 2), (8, 20)];
+        let outline = drawn(|buffer, white| buffer.draw_polygon(&triangle, white));
+        assert!(block((0, 2), (15, 2))
+            .iter()
+            .all(|pixel| outline.contains(pixel)));
+        assert!(outline.contains(&(2, 11)) && outline.contains(&(14, 11)));
+        assert!(!outline.contains(&(8, 6)) && !outline.contains(&(0, 11)));
+        let filled = drawn(|buffer, white| buffer.fill_polygon(&triangle, white));
+        assert!(filled.contains(&(8, 6)) && filled.contains(&(0, 2)) && filled.contains(&(8, 11)));
+        assert!(!filled.contains(&(0, 11)) && !filled.contains(&(15, 11)));
+        assert!(!filled.iter().any(|&(_, y)| y < 2));
+
+        // The fill stops at the outline and at the edges of the buffer.
+        let mut buffer = buffer();
+        let red = Pixel::new(255, 0, 0);
+        buffer.draw_rect((-5, 3), Resolution::new(10, 5), Pixel::white());
+        buffer.flood_fill((1, 5), red);
+        let flooded: Vec<(u16, u16)> = buffer
+            .pixels()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &pixel)| pixel == red)
+            .map(|(index, _)| xy(index, buffer.resolution()).into())
+            .collect();
+        assert_eq!(flooded, block((0, 4), (3, 6)));
+    }
+
+    #[test]
+    fn fill_square_and_triangle() {
+        let square =
+            drawn(|buffer, white| buffer.fill_polygon(&[(2, 2), (6, 2), (6, 6), (2, 6)], white));
+        assert_eq!(square, block((2, 2), (5, 5)));
+        // Pixels centered on the slanted edge are left to whatever lies beyond it.
+        let triangle = drawn(|buffer, white| buffer.fill_polygon(&[(0, 0), (8, 0), (0, 8)], white));
+        let expected: Vec<(u16, u16)> = block((0, 0), (7, 7))
+            .into_iter()
+            .filter(|&(x, y)| x + y < 7)
+            .collect();
+        assert_eq!(triangle, expected);
+        // Fewer than three points enclose nothing.
+        assert!(drawn(|buffer, white| buffer.fill_polygon(&[(1, 1), (9, 9)], white)).is_empty());
+        assert!(drawn(|buffer, white| buffer.fill_polygon(&[], white)).is_empty());
+    }
+
+    #[test]
+    fn self_intersecting_polygon() {
+        // A five-pointed star drawn in one stroke, its middle is enclosed twice.
+        let star = [(8, 0), (13, 11), (1, 4), (15, 4), (3, 11)];
+        let filled = drawn(|buffer, white| buffer.fill_polygon(&star, white));
+        assert!(filled.contains(&(8, 2)) && filled.contains(&(3, 4)) && filled.contains(&(13, 4)));
+        assert!(filled.contains(&(5, 9)) && filled.contains(&(10, 9)));
+        assert!(!filled.contains(&(8, 6)) && !filled.contains(&(7, 7)));
+        // A bowtie's two halves meet in a point and both get filled.
+        let bowtie = drawn(|buffer, white| {
+            buffer.fill_polygon(&[(0, 0), (10, 10), (10, 0), (0, 10)], white);
+        });
+        assert!(bowtie.contains(&(1, 3)) && bowtie.contains(&(8, 3)));
+        assert!(!bowtie.contains(&(5, 1)) && !bowtie.contains(&(5, 9)));
+    }
+
+    #[test]
+    fn huge_radii() {
+        let mut buffer = buffer();
+        buffer.draw_circle((8, 6), u16::MAX, Pixel::white());
+        // A circle that large has its rim far outside the buffer.
+        assert!(lit(&buffer).is_empty());
+        buffer.fill_ellipse((8, 6), (u16::MAX, 40_000), Pixel::white());
+        assert_eq!(lit(&buffer).len(), buffer.resolution().area());
+    }
+}
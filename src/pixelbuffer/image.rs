@@ -0,0 +1,402 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::gfx::{Pixel, PixelBuffer};
+use crate::ufb::Resolution;
+
+const PNG_SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(io::Error),
+    Png(png::DecodingError),
+    PngEncoding(png::EncodingError),
+    Gif(gif::EncodingError),
+    // Neither a PNG nor a PPM file.
+    Unsupported,
+    Malformed(String),
+    TooLarge((u32, u32)),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Io(ref err) => write!(f, "couldn't access the image: {err}"),
+            Self::Png(ref err) => write!(f, "couldn't decode the PNG: {err}"),
+            Self::PngEncoding(ref err) => write!(f, "couldn't encode the PNG: {err}"),
+            Self::Gif(ref err) => write!(f, "couldn't encode the GIF: {err}"),
+            Self::Unsupported => write!(f, "only PNG and PPM images are supported"),
+            Self::Malformed(ref reason) => write!(f, "malformed PPM: {reason}"),
+            Self::TooLarge((width, height)) => {
+                write!(
+                    f,
+                    "a {width}x{height} image doesn't fit into a pixel buffer"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<io::Error> for ImageError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(err: png::DecodingError) -> Self {
+        Self::Png(err)
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(err: png::EncodingError) -> Self {
+        Self::PngEncoding(err)
+    }
+}
+
+impl From<gif::EncodingError> for ImageError {
+    fn from(err: gif::EncodingError) -> Self {
+        Self::Gif(err)
+    }
+}
+
+/// Writes `buffer` to `path`, as a binary PPM if the extension is `ppm` and as a PNG otherwise.
+///
+/// # Errors
+/// Fails if the file can't be written.
+pub fn write_image(buffer: &PixelBuffer, path: impl AsRef<Path>) -> Result<(), ImageError> {
+    let ppm = path
+        .as_ref()
+        .extension()
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("ppm"));
+    let mut writer = BufWriter::new(File::create(path)?);
+    if ppm {
+        buffer.write_ppm(&mut writer)?;
+    } else {
+        buffer.write_png(&mut writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub(crate) fn encode_png(buffer: &PixelBuffer, writer: impl Write) -> Result<(), ImageError> {
+    let (width, height) = buffer.resolution().into();
+    let mut encoder = png::Encoder::new(writer, u32::from(width), u32::from(height));
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&raster(buffer.pixels()))?;
+    Ok(())
+}
+
+pub(crate) fn encode_ppm(buffer: &PixelBuffer, mut writer: impl Write) -> io::Result<()> {
+    let (width, height) = buffer.resolution().into();
+    write!(writer, "P6\n{width} {height}\n255\n")?;
+    writer.write_all(&raster(buffer.pixels()))
+}
+
+// Packed 8-bit RGB samples in row order, the layout every format here stores.
+pub(crate) fn raster(pixels: &[Pixel]) -> Vec<u8> {
+    pixels
+        .iter()
+        .flat_map(|&pixel| <[u8; 3]>::from(pixel))
+        .collect()
+}
+
+/// Reads a PNG or PPM (`P3` or `P6`) file, telling them apart by their content.
+///
+/// # Errors
+/// Fails if the file can't be read or isn't a valid image of a supported format.
+pub fn read_image(path: impl AsRef<Path>) -> Result<PixelBuffer, ImageError> {
+    let bytes = std::fs::read(path)?;
+    decode_image(&bytes)
+}
+
+/// Decodes a PNG or PPM image from memory.
+///
+/// # Errors
+/// Fails if the bytes aren't a valid image of a supported format.
+pub fn decode_image(bytes: &[u8]) -> Result<PixelBuffer, ImageError> {
+    if bytes.starts_with(PNG_SIGNATURE) {
+        decode_png(bytes)
+    } else if bytes.starts_with(b"P3") || bytes.starts_with(b"P6") {
+        decode_ppm(bytes)
+    } else {
+        Err(ImageError::Unsupported)
+    }
+}
+
+fn decode_png(bytes: &[u8]) -> Result<PixelBuffer, ImageError> {
+    let mut decoder = png::Decoder::new(bytes);
+    // Palettes and low bit depths are expanded, so every sample ends up as one byte.
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+    let resolution = resolution(info.width, info.height)?;
+    data.truncate(info.buffer_size());
+    let pixels = data
+        .chunks_exact(info.color_type.samples())
+        .map(|samples| match *samples {
+            [r, g, b] | [r, g, b, _] => Pixel::new(r, g, b),
+            [value] | [value, _] => Pixel::new(value, value, value),
+            _ => Pixel::black(),
+        })
+        .collect();
+    from_pixels(resolution, pixels)
+}
+
+fn decode_ppm(bytes: &[u8]) -> Result<PixelBuffer, ImageError> {
+    let mut header = PpmHeader { bytes, pos: 0 };
+    let plain = header.token() == Some(b"P3".as_slice());
+    let width = header.number("width")?;
+    let height = header.number("height")?;
+    let max = header.number("maximum value")?;
+    if max == 0 || max > u32::from(u16::MAX) {
+        return Err(ImageError::Malformed(format!(
+            "invalid maximum value {max}"
+        )));
+    }
+    let resolution = resolution(width, height)?;
+    let samples = resolution.area() * 3;
+    let values: Vec<u32> = if plain {
+        (0..samples)
+            .map(|_| header.number("sample"))
+            .collect::<Result<_, _>>()?
+    } else {
+        // A single whitespace character separates the header from the raster.
+        let raster = bytes.get(header.pos + 1..).unwrap_or_default();
+        let wide = max > 255;
+        let size = if wide { 2 } else { 1 };
+        if raster.len() < samples * size {
+            return Err(ImageError::Malformed("raster is too short".to_owned()));
+        }
+        raster
+            .chunks_exact(size)
+            .take(samples)
+            .map(|sample| match *sample {
+                [high, low] => u32::from(u16::from_be_bytes([high, low])),
+                [value] => u32::from(value),
+                _ => 0,
+            })
+            .collect()
+    };
+    #[allow(clippy::integer_division)]
+    let scale = |value: u32| u8::try_from(value.min(max) * 255 / max).unwrap_or(u8::MAX);
+    let pixels = values
+        .chunks_exact(3)
+        .map(|rgb| match *rgb {
+            [r, g, b] => Pixel::new(scale(r), scale(g), scale(b)),
+            _ => Pixel::black(),
+        })
+        .collect();
+    from_pixels(resolution, pixels)
+}
+
+fn resolution(width: u32, height: u32) -> Result<Resolution, ImageError> {
+    match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(w), Ok(h)) => Ok(Resolution::new(w, h)),
+        _ => Err(ImageError::TooLarge((width, height))),
+    }
+}
+
+fn from_pixels(resolution: Resolution, pixels: Vec<Pixel>) -> Result<PixelBuffer, ImageError> {
+    PixelBuffer::from_pixels(resolution, pixels)
+        .ok_or_else(|| ImageError::Malformed("not enough pixels".to_owned()))
+}
+
+// Whitespace separated header fields of a PPM file, with `#` comments running to the line end.
+struct PpmHeader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PpmHeader<'a> {
+    fn token(&mut self) -> Option<&'a [u8]> {
+        while let Some(&byte) = self.bytes.get(self.pos) {
+            if byte == b'#' {
+                while self.bytes.get(self.pos).map_or(false, |&b| b != b'\n') {
+                    self.pos += 1;
+                }
+            } else if byte.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .map_or(false, |b| !b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        self.bytes
+            .get(start..self.pos)
+            .filter(|token| !token.is_empty())
+    }
+
+    fn number(&mut self, name: &str) -> Result<u32, ImageError> {
+        self.token()
+            .and_then(|token| std::str::from_utf8(token).ok())
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| ImageError::Malformed(format!("missing or invalid {name}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Pixel = Pixel::new(255, 0, 0);
+    const BLUE: Pixel = Pixel::new(0, 0, 255);
+
+    fn png(color: png::ColorType, (width, height): (u32, u32), data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(data))
+            .expect("the PNG can be encoded");
+        bytes
+    }
+
+    fn malformed(bytes: &[u8]) -> String {
+        match decode_image(bytes) {
+            Err(ImageError::Malformed(reason)) => reason,
+            Err(err) => panic!("expected a malformed image, got {err}"),
+            Ok(_) => panic!("expected a malformed image"),
+        }
+    }
+
+    #[test]
+    fn plain_ppm_with_comments() {
+        let image =
+            decode_image(b"P3\n# a comment\n2 1 # width and height\n255\n255 0 0\n0 0 255\n")
+                .expect("the PPM is valid");
+        assert_eq!(<(u16, u16)>::from(image.resolution()), (2, 1));
+        assert_eq!(image.pixels(), &[RED, BLUE]);
+    }
+
+    #[test]
+    fn binary_ppm() {
+        let image =
+            decode_image(b"P6 2 1 255\n\xff\x00\x00\x00\x00\xff").expect("the PPM is valid");
+        assert_eq!(image.pixels(), &[RED, BLUE]);
+    }
+
+    #[test]
+    fn ppm_samples_are_scaled_to_bytes() {
+        let image = decode_image(b"P3 1 1 15 15 0 5").expect("the PPM is valid");
+        assert_eq!(image.pixels(), &[Pixel::new(255, 0, 85)]);
+        // Above 255 every sample takes two big-endian bytes.
+        let image =
+            decode_image(b"P6 1 1 65535\n\xff\xff\x00\x00\x80\x00").expect("the PPM is valid");
+        assert_eq!(image.pixels(), &[Pixel::new(255, 0, 127)]);
+    }
+
+    #[test]
+    fn malformed_ppm() {
+        assert_eq!(
+            malformed(b"P6 2 1 255\n\x01\x02\x03\x04\x05"),
+            "raster is too short"
+        );
+        assert_eq!(
+            malformed(b"P3 2 1 255 1 2 3 4 5"),
+            "missing or invalid sample"
+        );
+        assert_eq!(malformed(b"P3 1 1 0 0 0 0"), "invalid maximum value 0");
+        assert_eq!(malformed(b"P3 one 1"), "missing or invalid width");
+        assert!(matches!(
+            decode_image(b"P6 70000 1 255\n"),
+            Err(ImageError::TooLarge((70000, 1)))
+        ));
+        assert!(matches!(
+            decode_image(b"GIF89a"),
+            Err(ImageError::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn png_color_types() {
+        let image = decode_image(&png(png::ColorType::Rgb, (2, 1), &[255, 0, 0, 0, 0, 255]))
+            .expect("the PNG is valid");
+        assert_eq!(image.pixels(), &[RED, BLUE]);
+        // Alpha is dropped and gray is spread over every channel.
+        let image = decode_image(&png(png::ColorType::Rgba, (1, 1), &[255, 0, 0, 10]))
+            .expect("the PNG is valid");
+        assert_eq!(image.pixels(), &[RED]);
+        let image = decode_image(&png(png::ColorType::Grayscale, (1, 2), &[0, 200]))
+            .expect("the PNG is valid");
+        assert_eq!(<(u16, u16)>::from(image.resolution()), (1, 2));
+        assert_eq!(image.pixels(), &[Pixel::black(), Pixel::new(200, 200, 200)]);
+    }
+
+    #[test]
+    fn truncated_png() {
+        let bytes = png(png::ColorType::Rgb, (2, 1), &[255, 0, 0, 0, 0, 255]);
+        // Cut off in the middle of the image data.
+        let truncated = bytes.get(..bytes.len() - 20).unwrap_or_default();
+        assert!(matches!(decode_image(truncated), Err(ImageError::Png(_))));
+    }
+
+    // Every pixel different, so rows or channels swapped on the way would show.
+    fn gradient() -> PixelBuffer {
+        let mut buffer = PixelBuffer::new(Resolution::new(5, 3));
+        for y in 0..3_u8 {
+            for x in 0..5_u8 {
+                let pixel = Pixel::new(1 + 50 * x, 80 * y, 7);
+                buffer.set_pixel((u16::from(x), u16::from(y)).into(), pixel);
+            }
+        }
+        buffer
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let buffer = gradient();
+        let mut bytes = Vec::new();
+        buffer
+            .write_png(&mut bytes)
+            .expect("the PNG can be written");
+        let decoded = decode_image(&bytes).expect("the PNG reads back");
+        assert_eq!(<(u16, u16)>::from(decoded.resolution()), (5, 3));
+        assert_eq!(decoded.pixels(), buffer.pixels());
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let buffer = gradient();
+        let mut bytes = Vec::new();
+        buffer
+            .write_ppm(&mut bytes)
+            .expect("the PPM can be written");
+        assert!(bytes.starts_with(b"P6\n5 3\n255\n"));
+        let decoded = decode_image(&bytes).expect("the PPM reads back");
+        assert_eq!(<(u16, u16)>::from(decoded.resolution()), (5, 3));
+        assert_eq!(decoded.pixels(), buffer.pixels());
+    }
+
+    #[test]
+    fn format_follows_the_extension() {
+        let buffer = gradient();
+        for name in ["png", "PPM"] {
+            let path =
+                std::env::temp_dir().join(format!("glfwsand-image-{}.{name}", std::process::id()));
+            write_image(&buffer, &path).expect("the image can be written");
+            let bytes = std::fs::read(&path).expect("the image was written");
+            std::fs::remove_file(&path).ok();
+            assert_eq!(bytes.starts_with(b"P6"), name == "PPM");
+            let decoded = decode_image(&bytes).expect("the image reads back");
+            assert_eq!(decoded.pixels(), buffer.pixels());
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use glfw::{Action, Key, Modifiers, MouseButton};
+
+use crate::ufb::Event;
+
+// First line of every log, bumped whenever the line format changes.
+const HEADER: &str = "glfwsand input log 4";
+
+// Names of the modifier flags in the order they're written, joined by `+`.
+const MODIFIERS: [(Modifiers, &str); 4] = [
+    (Modifiers::Shift, "Shift"),
+    (Modifiers::Control, "Control"),
+    (Modifiers::Alt, "Alt"),
+    (Modifiers::Super, "Super"),
+];
+
+// Every key glfw knows, to turn the codes in a log back into keys.
+const KEYS: [Key; 121] = [
+    Key::Space,
+    Key::Apostrophe,
+    Key::Comma,
+    Key::Minus,
+    Key::Period,
+    Key::Slash,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::Semicolon,
+    Key::Equal,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::LeftBracket,
+    Key::Backslash,
+    Key::RightBracket,
+    Key::GraveAccent,
+    Key::World1,
+    Key::World2,
+    Key::Escape,
+    Key::Enter,
+    Key::Tab,
+    Key::Backspace,
+    Key::Insert,
+    Key::Delete,
+    Key::Right,
+    Key::Left,
+    Key::Down,
+    Key::Up,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Home,
+    Key::End,
+    Key::CapsLock,
+    Key::ScrollLock,
+    Key::NumLock,
+    Key::PrintScreen,
+    Key::Pause,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::F16,
+    Key::F17,
+    Key::F18,
+    Key::F19,
+    Key::F20,
+    Key::F21,
+    Key::F22,
+    Key::F23,
+    Key::F24,
+    Key::F25,
+    Key::Kp0,
+    Key::Kp1,
+    Key::Kp2,
+    Key::Kp3,
+    Key::Kp4,
+    Key::Kp5,
+    Key::Kp6,
+    Key::Kp7,
+    Key::Kp8,
+    Key::Kp9,
+    Key::KpDecimal,
+    Key::KpDivide,
+    Key::KpMultiply,
+    Key::KpSubtract,
+    Key::KpAdd,
+    Key::KpEnter,
+    Key::KpEqual,
+    Key::LeftShift,
+    Key::LeftControl,
+    Key::LeftAlt,
+    Key::LeftSuper,
+    Key::RightShift,
+    Key::RightControl,
+    Key::RightAlt,
+    Key::RightSuper,
+    Key::Menu,
+    Key::Unknown,
+];
+
+#[derive(Debug)]
+pub enum InputLogError {
+    Io(io::Error),
+    // The first line isn't the header this version writes.
+    Header(String),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for InputLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Io(ref err) => write!(f, "couldn't access the input log: {err}"),
+            Self::Header(ref header) => {
+                write!(
+                    f,
+                    "expected an input log starting with `{HEADER}`, got `{header}`"
+                )
+            }
+            Self::Parse { line, ref message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for InputLogError {}
+
+impl From<io::Error> for InputLogError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Writes the events of every frame to a text file as they happen.
+///
+/// After the header and a `seed <seed>` line for the simulation, every line is one event:
+/// `<frame> key <code> <action> <modifiers>`, `<frame> button <code> <action> <modifiers>`,
+/// `<frame> cursor <x> <y>`, `<frame> scroll <x> <y>`, `<frame> resize <width> <height>`,
+/// `<frame> enter`, `<frame> leave` or `<frame> close`.
+/// Codes are glfw's numbers for keys and buttons, which don't change when their names do.
+/// Actions are `press`, `release` or `repeat`, modifiers are names like `Shift+Control` or `none`.
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+}
+
+impl InputRecorder {
+    /// Starts a log for a session whose randomness was seeded with `seed`.
+    ///
+    /// # Errors
+    /// Fails if the file can't be created.
+    pub fn create(path: impl AsRef<Path>, seed: u64) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{HEADER}")?;
+        writeln!(writer, "seed {seed}")?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    /// Appends the events of one frame, flushing so the log survives a crash.
+    ///
+    /// # Errors
+    /// Fails if the file can't be written.
+    pub fn record(&mut self, frame: u64, events: &[Event]) -> io::Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        for event in events {
+            match *event {
+                Event::Key(key, action, mods) => writeln!(
+                    self.writer,
+                    "{frame} key {} {} {}",
+                    key_code(key),
+                    action_name(action),
+                    modifier_names(mods)
+                )?,
+                Event::MouseButton(button, action, mods) => writeln!(
+                    self.writer,
+                    "{frame} button {} {} {}",
+                    button_code(button),
+                    action_name(action),
+                    modifier_names(mods)
+                )?,
+                Event::Cursor((x, y)) => writeln!(self.writer, "{frame} cursor {x} {y}")?,
+                Event::Scroll((x, y)) => writeln!(self.writer, "{frame} scroll {x} {y}")?,
+                Event::Resize((width, height)) => {
+                    writeln!(self.writer, "{frame} resize {width} {height}")?;
+                }
+                Event::CursorEnter(true) => writeln!(self.writer, "{frame} enter")?,
+                Event::CursorEnter(false) => writeln!(self.writer, "{frame} leave")?,
+                Event::Close => writeln!(self.writer, "{frame} close")?,
+            }
+        }
+        self.writer.flush()
+    }
+}
+
+/// Events read back from a file written by `InputRecorder`, handed out frame by frame.
+pub struct InputLog {
+    seed: u64,
+    // Sorted by frame.
+    events: Vec<(u64, Event)>,
+    next: usize,
+}
+
+impl InputLog {
+    /// # Errors
+    /// Fails if the file can't be read or isn't a valid input log.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, InputLogError> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        if header.trim() != HEADER {
+            return Err(InputLogError::Header(header));
+        }
+        let seed = lines
+            .next()
+            .transpose()?
+            .as_deref()
+            .and_then(|line| line.strip_prefix("seed "))
+            .and_then(|seed| seed.trim().parse().ok())
+            .ok_or_else(|| InputLogError::Parse {
+                line: 2,
+                message: "expected `seed <seed>`".to_owned(),
+            })?;
+        let mut events = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            // The header and the seed take up the first two lines.
+            let error = |message: String| InputLogError::Parse {
+                line: index + 3,
+                message,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let frame: u64 = fields
+                .next()
+                .and_then(|frame| frame.parse().ok())
+                .ok_or_else(|| error(format!("expected a frame number in {line}")))?;
+            let event = parse_event(&mut fields).map_err(error)?;
+            if events.last().map_or(false, |&(last, _)| last > frame) {
+                return Err(error(format!("frame {frame} is out of order")));
+            }
+            events.push((frame, event));
+        }
+        Ok(Self {
+            seed,
+            events,
+            next: 0,
+        })
+    }
+
+    /// Seed the recorded session was simulated with.
+    #[must_use]
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Events recorded for `frame`, frames have to be asked for in increasing order.
+    pub fn events(&mut self, frame: u64) -> Vec<Event> {
+        let mut events = Vec::new();
+        while let Some(&(recorded, event)) = self.events.get(self.next) {
+            if recorded > frame {
+                break;
+            }
+            if recorded == frame {
+                events.push(event);
+            }
+            self.next += 1;
+        }
+        events
+    }
+
+    /// Number of frames needed to replay every recorded event.
+    #[must_use]
+    pub fn frames(&self) -> u64 {
+        self.events.last().map_or(0, |&(frame, _)| frame + 1)
+    }
+}
+
+const fn action_name(action: Action) -> &'static str {
+    match action {
+        Action::Press => "press",
+        Action::Release => "release",
+        Action::Repeat => "repeat",
+    }
+}
+
+fn modifier_names(mods: Modifiers) -> String {
+    let names: Vec<&str> = MODIFIERS
+        .iter()
+        .filter(|&&(flag, _)| mods.contains(flag))
+        .map(|&(_, name)| name)
+        .collect();
+    if names.is_empty() {
+        "none".to_owned()
+    } else {
+        names.join("+")
+    }
+}
+
+fn parse_action(name: &str) -> Result<Action, String> {
+    match name {
+        "press" => Ok(Action::Press),
+        "release" => Ok(Action::Release),
+        "repeat" => Ok(Action::Repeat),
+        _ => Err(format!("unknown action {name}")),
+    }
+}
+
+fn parse_modifiers(names: &str) -> Result<Modifiers, String> {
+    if names == "none" {
+        return Ok(Modifiers::empty());
+    }
+    names.split('+').try_fold(Modifiers::empty(), |mods, name| {
+        MODIFIERS
+            .iter()
+            .find(|&&(_, known)| known == name)
+            .map(|&(flag, _)| mods | flag)
+            .ok_or_else(|| format!("unknown modifier {name}"))
+    })
+}
+
+// glfw's numbers for keys and buttons, the same ones its C API uses.
+// Both are fieldless `repr(i32)` enums, so the casts can't lose anything.
+#[allow(clippy::as_conversions)]
+const fn key_code(key: Key) -> i32 {
+    key as i32
+}
+
+#[allow(clippy::as_conversions)]
+const fn button_code(button: MouseButton) -> i32 {
+    button as i32
+}
+
+// Keys by their glfw code, built on first use.
+fn key_codes() -> &'static HashMap<i32, Key> {
+    static CODES: OnceLock<HashMap<i32, Key>> = OnceLock::new();
+    CODES.get_or_init(|| KEYS.into_iter().map(|key| (key_code(key), key)).collect())
+}
+
+fn parse_event<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Result<Event, String> {
+    let kind = fields.next().unwrap_or_default();
+    let mut next = |what: &str| fields.next().ok_or_else(|| format!("missing {what}"));
+    match kind {
+        "key" => {
+            let code = next("key code")?;
+            let key = code
+                .parse()
+                .ok()
+                .and_then(|code: i32| key_codes().get(&code).copied())
+                .ok_or_else(|| format!("unknown key code {code}"))?;
+            let action = parse_action(next("key action")?)?;
+            Ok(Event::Key(
+                key,
+                action,
+                parse_modifiers(next("modifiers")?)?,
+            ))
+        }
+        "button" => {
+            let code = next("button code")?;
+            let button = code
+                .parse()
+                .ok()
+                .and_then(MouseButton::from_i32)
+                .ok_or_else(|| format!("unknown mouse button {code}"))?;
+            let action = parse_action(next("button action")?)?;
+            Ok(Event::MouseButton(
+                button,
+                action,
+                parse_modifiers(next("modifiers")?)?,
+            ))
+        }
+        "cursor" | "scroll" => {
+            let mut coordinate = || {
+                next("coordinate")?
+                    .parse()
+                    .map_err(|_| format!("invalid {kind} coordinate"))
+            };
+            let position = (coordinate()?, coordinate()?);
+            Ok(if kind == "cursor" {
+                Event::Cursor(position)
+            } else {
+                Event::Scroll(position)
+            })
+        }
+        "resize" => {
+            let mut size = || {
+                next("size")?
+                    .parse()
+                    .map_err(|_| "invalid window size".to_owned())
+            };
+            Ok(Event::Resize((size()?, size()?)))
+        }
+        "enter" => Ok(Event::CursorEnter(true)),
+        "leave" => Ok(Event::CursorEnter(false)),
+        "close" => Ok(Event::Close),
+        _ => Err(format!("unknown event {kind}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let path = std::env::temp_dir().join(format!("glfwsand-input-{}.log", std::process::id()));
+        let held = Modifiers::Shift | Modifiers::Control;
+        let events = [
+            Event::Key(Key::Space, Action::Press, Modifiers::empty()),
+            Event::Key(Key::KpEnter, Action::Repeat, held),
+            Event::Key(Key::Unknown, Action::Release, Modifiers::Super),
+            Event::MouseButton(MouseButton::Button2, Action::Press, Modifiers::Alt),
+            Event::Cursor((12.5, -3.0)),
+            Event::Scroll((0.0, 1.0)),
+            Event::Resize((640, 480)),
+            Event::CursorEnter(false),
+            Event::Close,
+        ];
+        let mut recorder = InputRecorder::create(&path, 42).expect("the log can be created");
+        recorder.record(3, &events).expect("the log can be written");
+        drop(recorder);
+        let mut log = InputLog::read(&path).expect("the log reads back");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(log.seed(), 42);
+        assert_eq!(log.frames(), 4);
+        assert!(log.events(2).is_empty());
+        assert_eq!(log.events(3), events);
+    }
+
+    #[test]
+    fn every_key_code_is_unique() {
+        assert_eq!(key_codes().len(), KEYS.len());
+    }
+}
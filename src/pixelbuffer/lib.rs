@@ -38,3 +38,18 @@ pub use gfx::*;
 
 pub mod ufb;
 pub use ufb::*;
+
+pub mod font;
+pub use font::*;
+
+pub mod image;
+pub use image::*;
+
+pub mod recorder;
+pub use recorder::*;
+
+pub mod input;
+pub use input::*;
+
+pub mod presenter;
+pub use presenter::*;
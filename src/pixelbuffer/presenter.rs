@@ -0,0 +1,69 @@
+use std::collections::VecDeque;
+
+use crate::gfx::{Pixel, PixelBuffer};
+use crate::ufb::{Event, Resolution};
+
+/// Somewhere finished frames are shown and input comes from, like a `Window`.
+pub trait Presenter {
+    /// Shows `frame` until the next one is presented.
+    fn present(&mut self, frame: Vec<Pixel>);
+
+    /// Input received since the last poll.
+    fn poll_events(&mut self) -> Vec<Event>;
+
+    /// Switches between windowed and fullscreen, backends without a screen ignore it.
+    fn toggle_fullscreen(&mut self) {}
+}
+
+/// Presenter without a display that keeps every frame and replays scripted input,
+/// so the simulation can run where no window can be opened.
+pub struct MemoryPresenter {
+    resolution: Resolution,
+    frames: Vec<PixelBuffer>,
+    // Events for each upcoming poll, in order.
+    script: VecDeque<Vec<Event>>,
+}
+
+impl MemoryPresenter {
+    #[must_use]
+    pub const fn new(resolution: Resolution) -> Self {
+        Self {
+            resolution,
+            frames: Vec::new(),
+            script: VecDeque::new(),
+        }
+    }
+
+    /// Queues `events` to be returned by one poll, after those scripted before.
+    /// Polls past the end of the script report `Event::Close`, so a scripted run always ends.
+    pub fn script(&mut self, events: Vec<Event>) {
+        self.script.push_back(events);
+    }
+
+    /// Every frame presented so far, oldest first.
+    #[must_use]
+    pub fn frames(&self) -> &[PixelBuffer] {
+        &self.frames
+    }
+
+    #[must_use]
+    pub fn last_frame(&self) -> Option<&PixelBuffer> {
+        self.frames.last()
+    }
+}
+
+impl Presenter for MemoryPresenter {
+    /// # Panics
+    /// If the frame doesn't match the resolution the presenter was created with.
+    fn present(&mut self, frame: Vec<Pixel>) {
+        let frame = PixelBuffer::from_pixels(self.resolution, frame)
+            .expect("Presented a frame of the wrong size.");
+        self.frames.push(frame);
+    }
+
+    fn poll_events(&mut self) -> Vec<Event> {
+        self.script
+            .pop_front()
+            .unwrap_or_else(|| vec![Event::Close])
+    }
+}
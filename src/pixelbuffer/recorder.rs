@@ -0,0 +1,190 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use crate::gfx::{Pixel, PixelBuffer};
+use crate::image::{raster, ImageError};
+use crate::ufb::Resolution;
+
+// Time every GIF frame is shown, in hundredths of a second, close to the 60 Hz of the window.
+const GIF_FRAME_DELAY: u16 = 2;
+// NeuQuant sampling factor from 1 (best colors) to 30 (fastest), balanced for live capture.
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+
+enum Target {
+    Gif(gif::Encoder<BufWriter<File>>),
+    // Directory the numbered frames are written into.
+    PngSequence(PathBuf),
+}
+
+/// Encodes consecutive frames into an animated GIF or a numbered PNG sequence as they arrive.
+pub struct Recorder {
+    target: Target,
+    resolution: Resolution,
+    frames: usize,
+}
+
+impl Recorder {
+    /// Starts an animated GIF at `path`, every frame gets its own quantized palette.
+    ///
+    /// # Errors
+    /// Fails if the file can't be created.
+    pub fn gif(path: impl Into<PathBuf>, resolution: Resolution) -> Result<Self, ImageError> {
+        let file = BufWriter::new(File::create(path.into())?);
+        let (width, height) = resolution.into();
+        let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        Ok(Self {
+            target: Target::Gif(encoder),
+            resolution,
+            frames: 0,
+        })
+    }
+
+    /// Starts writing frames as `frame-00000.png` and onwards into the directory `path`.
+    ///
+    /// # Errors
+    /// Fails if the directory can't be created.
+    pub fn png_sequence(
+        path: impl Into<PathBuf>,
+        resolution: Resolution,
+    ) -> Result<Self, ImageError> {
+        let path = path.into();
+        std::fs::create_dir_all(&path)?;
+        Ok(Self {
+            target: Target::PngSequence(path),
+            resolution,
+            frames: 0,
+        })
+    }
+
+    /// Encodes the next frame, which has to match the resolution the recorder was started with.
+    ///
+    /// # Errors
+    /// Fails if the frame has the wrong size or can't be written.
+    pub fn capture(&mut self, frame: &[Pixel]) -> Result<(), ImageError> {
+        if frame.len() != self.resolution.area() {
+            return Err(ImageError::Malformed(format!(
+                "expected a frame of {} pixels, got {}",
+                self.resolution.area(),
+                frame.len()
+            )));
+        }
+        match self.target {
+            Target::Gif(ref mut encoder) => {
+                let (width, height) = self.resolution.into();
+                let mut gif_frame = gif::Frame::from_rgb_speed(
+                    width,
+                    height,
+                    &raster(frame),
+                    GIF_QUANTIZATION_SPEED,
+                );
+                gif_frame.delay = GIF_FRAME_DELAY;
+                encoder.write_frame(&gif_frame)?;
+            }
+            Target::PngSequence(ref directory) => {
+                let path = directory.join(format!("frame-{:05}.png", self.frames));
+                let buffer = PixelBuffer::from_pixels(self.resolution, frame.to_vec())
+                    .ok_or_else(|| ImageError::Malformed("frame has the wrong size".to_owned()))?;
+                let mut writer = BufWriter::new(File::create(path)?);
+                buffer.write_png(&mut writer)?;
+                writer.flush()?;
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    #[must_use]
+    pub const fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Completes the recording and returns how many frames it holds.
+    ///
+    /// # Errors
+    /// Fails if the end of the GIF can't be written.
+    pub fn finish(self) -> Result<usize, ImageError> {
+        if let Target::Gif(encoder) = self.target {
+            encoder.into_inner()?.flush()?;
+        }
+        Ok(self.frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLUTION: Resolution = Resolution::new(4, 3);
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("glfwsand-{}-{name}", std::process::id()))
+    }
+
+    fn frame(pixel: Pixel) -> Vec<Pixel> {
+        vec![pixel; RESOLUTION.area()]
+    }
+
+    #[test]
+    fn wrong_frame_size() {
+        let directory = temp_path("wrong-size");
+        let mut recorder =
+            Recorder::png_sequence(&directory, RESOLUTION).expect("the directory can be created");
+        let result = recorder.capture(&[Pixel::white(); 5]);
+        let written = std::fs::read_dir(&directory).map(Iterator::count);
+        std::fs::remove_dir_all(&directory).ok();
+        assert!(matches!(result, Err(ImageError::Malformed(_))));
+        assert_eq!(recorder.frames(), 0);
+        assert_eq!(written.ok(), Some(0));
+    }
+
+    #[test]
+    fn png_sequence() {
+        let directory = temp_path("sequence");
+        let mut recorder =
+            Recorder::png_sequence(&directory, RESOLUTION).expect("the directory can be created");
+        for pixel in [Pixel::white(), Pixel::new(10, 20, 30)] {
+            recorder
+                .capture(&frame(pixel))
+                .expect("the frame is written");
+        }
+        let frames = recorder.finish();
+        let last = crate::image::read_image(directory.join("frame-00001.png"));
+        let mut names: Vec<String> = std::fs::read_dir(&directory)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .collect();
+        names.sort();
+        std::fs::remove_dir_all(&directory).ok();
+        assert_eq!(frames.ok(), Some(2));
+        assert_eq!(names, ["frame-00000.png", "frame-00001.png"]);
+        let last = last.expect("the frame reads back");
+        assert_eq!(last.pixels(), frame(Pixel::new(10, 20, 30)));
+    }
+
+    #[test]
+    fn gif() {
+        let path = temp_path("recording.gif");
+        let mut recorder = Recorder::gif(&path, RESOLUTION).expect("the file can be created");
+        for pixel in [Pixel::white(), Pixel::black(), Pixel::new(200, 0, 0)] {
+            recorder
+                .capture(&frame(pixel))
+                .expect("the frame is encoded");
+        }
+        let finished = recorder.finish();
+        let file = File::open(&path).expect("the GIF was written");
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(file)
+            .expect("the GIF has a valid header");
+        let mut frame_count = 0;
+        while let Ok(Some(_)) = decoder.read_next_frame() {
+            frame_count += 1;
+        }
+        std::fs::remove_file(&path).ok();
+        assert_eq!(finished.ok(), Some(3));
+        assert_eq!((decoder.width(), decoder.height()), (4, 3));
+        assert_eq!(frame_count, 3);
+    }
+}
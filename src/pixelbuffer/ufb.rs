@@ -1,49 +1,94 @@
 extern crate glfw;
-use glu_sys::glu::{glDrawPixels, glPixelZoom, glRasterPos2i, GL_RGB, GL_UNSIGNED_BYTE};
+use glu_sys::glu::{
+    glClear, glDrawPixels, glPixelZoom, glRasterPos2i, glViewport, GL_COLOR_BUFFER_BIT, GL_RGB,
+    GL_UNSIGNED_BYTE,
+};
 
-use glfw::{Action, Context, Key, MouseButton};
+use glfw::{Action, Context, Key, Modifiers, MouseButton};
 use std::convert::{From, Into};
 
 use crate::gfx::Pixel;
+use crate::presenter::Presenter;
 
 /// Wrapper around a glfw window
 pub struct Window {
     glfw: glfw::Glfw,
     window: glfw::Window,
+    // Size of the frames drawn into the window, in logical pixels.
     resolution: Resolution,
+    // Framebuffer pixels per logical pixel along each axis, the largest that fits the window.
+    scale: u16,
+    // Bottom left corner of the frame in the framebuffer, the rest is left black.
+    offset: (i32, i32),
+    // Position and size to restore when leaving fullscreen, `None` while windowed.
+    windowed: Option<(i32, i32, i32, i32)>,
     events: std::sync::mpsc::Receiver<(f64, glfw::WindowEvent)>,
     frame: Option<Vec<Pixel>>,
 }
 
 impl Window {
-    /// Instantiate a window
+    /// Instantiate a window showing frames of `res` logical pixels,
+    /// each drawn as a `scale` by `scale` square of screen pixels until the window is resized.
     #[must_use]
-    pub fn new(res: Resolution, title: &str) -> Self {
+    pub fn new(res: Resolution, scale: u16, title: &str) -> Self {
         let (w, h) = res.into();
+        let scale = scale.max(1);
         let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).expect("Couldn't initialize the window.");
         let (mut window, events) = glfw
             .create_window(
-                u32::from(w),
-                u32::from(h),
+                u32::from(w) * u32::from(scale),
+                u32::from(h) * u32::from(scale),
                 title,
                 glfw::WindowMode::Windowed,
             )
             .expect("Couldn't initialize the window.");
-        window.set_resizable(false);
+        window.set_resizable(true);
         // Polling https://github.com/PistonDevelopers/glfw-rs/blob/master/examples/events.rs
         window.set_key_polling(true);
         window.set_cursor_pos_polling(true);
         window.set_close_polling(true);
         window.set_mouse_button_polling(true);
+        window.set_scroll_polling(true);
+        window.set_cursor_enter_polling(true);
+        window.set_size_polling(true);
+        window.set_framebuffer_size_polling(true);
         window.make_current();
         glfw.set_swap_interval(glfw::SwapInterval::Sync(1));
-        Self {
+        let mut window = Self {
             glfw,
             window,
             resolution: Resolution::new(w, h),
+            scale,
+            offset: (0, 0),
+            windowed: None,
             events,
             frame: Some(vec![Pixel::new(0, 50, 80); usize::from(w) * usize::from(h)]),
-        }
+        };
+        window.layout();
+        window
+    }
+
+    // Picks the largest integer scale the framebuffer fits and centers the frame in it.
+    fn layout(&mut self) {
+        let (fb_width, fb_height) = self.window.get_framebuffer_size();
+        let (width, height) = self.resolution.into();
+        let (width, height) = (i32::from(width), i32::from(height));
+        #[allow(clippy::integer_division)]
+        let fit = (fb_width / width).min(fb_height / height);
+        // A window smaller than the frame, or a minimized one, still gets the frame unscaled.
+        self.scale = u16::try_from(fit).unwrap_or(1).max(1);
+        let (width, height) = (
+            width * i32::from(self.scale),
+            height * i32::from(self.scale),
+        );
+        #[allow(clippy::integer_division)]
+        let offset = ((fb_width - width) / 2, (fb_height - height) / 2);
+        self.offset = offset;
+    }
+
+    #[must_use]
+    pub const fn is_fullscreen(&self) -> bool {
+        self.windowed.is_some()
     }
 
     /// Get the internal buffer
@@ -53,10 +98,20 @@ impl Window {
 
     fn swap(&mut self) {
         if let Some(ref frame) = self.frame {
+            let (width, height) = self.resolution.into();
+            let scale = i32::from(self.scale);
             unsafe {
+                glClear(GL_COLOR_BUFFER_BIT);
+                // Clipping space spans the viewport, so its top left corner starts the frame.
+                glViewport(
+                    self.offset.0,
+                    self.offset.1,
+                    i32::from(width) * scale,
+                    i32::from(height) * scale,
+                );
                 glRasterPos2i(-1, 1);
-                glPixelZoom(1., -1.);
-                let (width, height) = self.resolution.into();
+                let zoom = f32::from(self.scale);
+                glPixelZoom(zoom, -zoom);
                 glDrawPixels(
                     i32::from(width),
                     i32::from(height),
@@ -69,23 +124,58 @@ impl Window {
         }
     }
 
+    // Converts a cursor position in screen coordinates to logical pixels of the frame.
+    fn to_logical(&self, x: f64, y: f64) -> (f64, f64) {
+        // Screen coordinates and framebuffer pixels differ on high density displays.
+        let (width, height) = self.window.get_size();
+        let (fb_width, fb_height) = self.window.get_framebuffer_size();
+        let density = (
+            f64::from(fb_width) / f64::from(width.max(1)),
+            f64::from(fb_height) / f64::from(height.max(1)),
+        );
+        let scale = f64::from(self.scale);
+        let (_, frame_height) = self.resolution.into();
+        // The offset counts from the bottom, the cursor from the top.
+        let top = fb_height - self.offset.1 - i32::from(frame_height) * i32::from(self.scale);
+        (
+            x.mul_add(density.0, -f64::from(self.offset.0)) / scale,
+            y.mul_add(density.1, -f64::from(top)) / scale,
+        )
+    }
+
     /// Add logic while the window is shown
     pub fn shown(&mut self) -> Vec<Event> {
         self.swap();
+        self.poll()
+    }
+
+    fn poll(&mut self) -> Vec<Event> {
         self.glfw.poll_events();
         let mut events = Vec::new();
-        for (_, event) in glfw::flush_messages(&self.events) {
+        // Collected first, as a resize changes the layout later events are converted with.
+        let messages: Vec<_> = glfw::flush_messages(&self.events).collect();
+        for (_, event) in messages {
             match event {
                 glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _)
                 | glfw::WindowEvent::Close => {
                     self.window.set_should_close(true);
                     events.push(Event::Close);
                 }
-                glfw::WindowEvent::Key(key, _, Action::Press, _) => events.push(Event::Key(key)),
-                glfw::WindowEvent::CursorPos(x, y) => events.push(Event::Cursor((x, y))),
-                glfw::WindowEvent::MouseButton(btn, _action, _mods) => {
-                    events.push(Event::MouseButton(btn));
+                glfw::WindowEvent::Key(key, _, action, mods) => {
+                    events.push(Event::Key(key, action, mods));
+                }
+                glfw::WindowEvent::CursorPos(x, y) => {
+                    events.push(Event::Cursor(self.to_logical(x, y)));
                 }
+                glfw::WindowEvent::MouseButton(btn, action, mods) => {
+                    events.push(Event::MouseButton(btn, action, mods));
+                }
+                glfw::WindowEvent::Scroll(x, y) => events.push(Event::Scroll((x, y))),
+                glfw::WindowEvent::CursorEnter(entered) => events.push(Event::CursorEnter(entered)),
+                glfw::WindowEvent::Size(width, height) => {
+                    events.push(Event::Resize((width, height)));
+                }
+                glfw::WindowEvent::FramebufferSize(..) => self.layout(),
                 _ => (),
             };
         }
@@ -93,11 +183,68 @@ impl Window {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Presenter for Window {
+    fn present(&mut self, frame: Vec<Pixel>) {
+        self.set_frame(frame);
+        self.swap();
+    }
+
+    fn poll_events(&mut self) -> Vec<Event> {
+        self.poll()
+    }
+
+    // Switches between a window and borderless fullscreen on the primary monitor.
+    fn toggle_fullscreen(&mut self) {
+        if let Some((x, y, width, height)) = self.windowed.take() {
+            self.window.set_monitor(
+                glfw::WindowMode::Windowed,
+                x,
+                y,
+                u32::try_from(width).unwrap_or(1),
+                u32::try_from(height).unwrap_or(1),
+                None,
+            );
+        } else {
+            let (x, y) = self.window.get_pos();
+            let (width, height) = self.window.get_size();
+            let window = &mut self.window;
+            // Keeping the monitor's current video mode makes glfw skip the mode switch.
+            let fullscreen = self.glfw.with_primary_monitor(|_, monitor| {
+                let monitor = monitor?;
+                let mode = monitor.get_video_mode()?;
+                window.set_monitor(
+                    glfw::WindowMode::FullScreen(monitor),
+                    0,
+                    0,
+                    mode.width,
+                    mode.height,
+                    Some(mode.refresh_rate),
+                );
+                Some(())
+            });
+            match fullscreen {
+                Some(()) => self.windowed = Some((x, y, width, height)),
+                None => println!("Couldn't find a monitor to go fullscreen on"),
+            }
+        }
+        self.layout();
+    }
+}
+
+/// Input reported by the window. Keys and buttons say whether they were pressed, released
+/// or held down long enough to repeat, along with the modifier keys held at the time.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Event {
-    Key(Key),
-    MouseButton(MouseButton),
+    Key(Key, Action, Modifiers),
+    MouseButton(MouseButton, Action, Modifiers),
+    // Cursor position in logical pixels from the top left corner of the frame.
     Cursor((f64, f64)),
+    // Horizontal and vertical wheel movement, positive is right and away from the user.
+    Scroll((f64, f64)),
+    // True when the cursor entered the window, false when it left.
+    CursorEnter(bool),
+    // New size of the window in screen coordinates.
+    Resize((i32, i32)),
     Close,
 }
 